@@ -1,37 +1,83 @@
 use std::{
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
-    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
 };
 
-use crate::{cmd::cli::Attach, config::Config, ui::Picker, util, walker::Walker};
+use crate::{
+    cmd::cli::Attach,
+    config::{Action, Config},
+    frecency::Frecency,
+    history::History,
+    index::Index,
+    mux::{Multiplexer, SessionInfo},
+    ui::{
+        Item, MatchMode, Picker, PickerOutcome, SessionPreview, UnifiedPreview, WorkspacePreview,
+    },
+};
 
 use gix::{bstr::ByteSlice, Repository};
 use itertools::Itertools;
 use miette::{miette, IntoDiagnostic, Result};
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 
-use super::Run;
+use super::{
+    kill::{kill_sessions, rename_session},
+    Run,
+};
 
 impl Run for Attach {
     fn run(self) -> Result<()> {
-        let config = Config::load()?;
+        self.execute(Config::load()?)
+    }
+}
+
+impl Attach {
+    pub fn execute(self, config: Config) -> Result<()> {
         let mux = &config.mux;
         let query = self.query.as_ref().map(|v| v.join(" "));
+        let match_mode = match self.exact {
+            true => MatchMode::Exact,
+            false => MatchMode::Fuzzy,
+        };
 
         if self.exists {
             let names = mux.list_sessions();
-            let selected = match names.len() {
+            let outcome = match names.len() {
                 0 => None,
-                1 => names.into_iter().next(),
+                1 => Some(PickerOutcome {
+                    action: Action::Accept,
+                    current: names[0].clone(),
+                    items: names,
+                }),
                 _ => Picker::new()
+                    .height(config.mode)
+                    .finder(&config.finder)
+                    .keys(&config.keys)
+                    .theme(&config.theme)
+                    .history(History::new()?)
+                    .match_mode(match_mode)
                     .items(&names)
+                    .preview(SessionPreview::new(mux.clone()), &config.preview)
+                    .on_kill(kill_sessions(mux.clone()))
+                    .on_rename(rename_session(mux.clone()))
                     .prompt("> ")
                     .filter(query.as_deref())
-                    .select()?,
+                    .select_many()?,
             };
 
-            if let Some(selected) = selected {
-                mux.attach_session(&selected)?;
+            // Marks are used to kill sessions, only the session under the cursor is attached to
+            if let Some(PickerOutcome {
+                action, current, ..
+            }) = outcome
+            {
+                let previous = mux.session_name();
+                mux.attach_session(&current)?;
+                if action == Action::AcceptAndKill {
+                    kill_previous(&config, previous, &current)?;
+                }
             }
 
             return Ok(());
@@ -40,30 +86,40 @@ impl Run for Attach {
         if let Some(path) = self.path.as_ref() {
             if path.as_path() == Path::new(".") {
                 let cwd = std::env::current_dir().into_diagnostic()?;
-                return self.execute_selected(&cwd, &config);
+                return self.execute_selected(&cwd, config);
             }
 
             if !path.exists() {
                 return Err(miette!("Path does not exist: '{}'", path.display()));
             }
 
-            return self.execute_selected(path, &config);
+            return self.execute_selected(path, config);
         }
 
-        let paths = config.paths_from_walk();
+        // Show the stored index right away and stream the result of a new walk into the picker.
+        // A query needs the complete list to check for a single match so the first run has to
+        // wait for the walk to complete.
+        let mut index = Index::new()?;
+        let mut walked = false;
+        let sessions = list_sessions(mux.as_ref(), self.unified);
 
         if let Some(query) = query.as_ref() {
-            // Check if there is one exact match if so then execute that
-            let matches = paths
-                .par_iter()
-                .filter(|v| v.contains(query))
-                .collect::<Vec<_>>();
+            if index.is_empty() {
+                index = Index::walk(&config)?;
+                walked = true;
+            }
+
+            // Check if there is only one match if so then execute that. Without `--exact` an
+            // item matches if it contains the query.
+            let items = unified_items(&sessions, &index.0);
+            let matches = match self.exact {
+                true => match_mode.filter(query, &items),
+                false => items.iter().filter(|item| item.contains(query)).collect(),
+            };
             if matches.len() == 1 {
-                return self.execute_selected(
-                    &PathBuf::from_str(matches.first().expect("Matches length is checked to be 1"))
-                        .into_diagnostic()?,
-                    &config,
-                );
+                let item = matches.first().expect("Matches length is checked to be 1");
+                let names = sessions.iter().map(|s| s.name.clone()).collect_vec();
+                return self.attach_item(Item::new(item, &names), config).map(drop);
             }
         }
 
@@ -74,44 +130,157 @@ impl Run for Attach {
         //     crate::ui::PickerSelection::None => todo!(),
         // };
 
-        let choice = match Picker::new()
-            .items(&paths)
+        let notes = sessions
+            .iter()
+            .map(|session| (session.name.clone(), session_note(session)))
+            .collect::<HashMap<_, _>>();
+        // Shared with the refresh so the unified picker is rebuilt with the workspaces it found
+        let workspaces = self.unified.then(|| Arc::new(Mutex::new(index.0.clone())));
+        let mut picker = Picker::new()
+            .height(config.mode)
+            .finder(&config.finder)
+            .keys(&config.keys)
+            .theme(&config.theme)
+            .history(History::new()?)
+            .match_mode(match_mode)
+            .items(&unified_items(&sessions, &index.0))
+            .notes(notes)
+            .frecency(&Frecency::new()?)
+            .on_kill(kill_item_sessions(mux.clone(), workspaces.clone()))
             .filter(query.as_deref())
-            .prompt("> ")
-            .select()?
-        {
-            Some(s) => s,
+            .prompt("> ");
+        picker = match self.unified {
+            true => picker.preview(UnifiedPreview::new(mux.clone()), &config.preview),
+            false => picker.preview(WorkspacePreview, &config.preview),
+        };
+        // Workspaces can only be opened as a window from inside of a session
+        if mux.session_name().is_some() {
+            picker = picker.open_window();
+        }
+        let refreshed = Arc::new(AtomicBool::new(walked));
+        if !walked {
+            let walk_config = config.clone();
+            let walk_mux = mux.clone();
+            let unified = self.unified;
+            let opened = session_paths(&sessions);
+            let refreshed = refreshed.clone();
+            picker = picker.refresh(move |injector| {
+                let paths = index
+                    .refresh(&walk_config, |path| {
+                        // The picker holds the injector while it rebuilds its items after a
+                        // kill, so each path is either rebuilt or pushed afterwards
+                        let injector = injector
+                            .lock()
+                            .expect("picker injector lock is not poisoned");
+                        if let Some(workspaces) = workspaces.as_ref() {
+                            lock(workspaces).push(path.to_string());
+                        }
+                        if !opened.contains(Path::new(path)) {
+                            Picker::push(&injector, path.to_string())
+                        }
+                    })
+                    .ok()
+                    .flatten();
+                if let (Some(workspaces), Some(paths)) = (workspaces.as_ref(), paths.as_ref()) {
+                    *lock(workspaces) = paths.clone();
+                }
+                refreshed.store(true, Ordering::Release);
+                paths.map(|paths| unified_items(&list_sessions(walk_mux.as_ref(), unified), &paths))
+            });
+        }
+
+        let outcome = picker.select()?;
+        // The walk would be cut short when this process exits. Finish it in the background
+        // instead of making the attach wait for it.
+        if !refreshed.load(Ordering::Acquire) {
+            let _ = Index::refresh_detached();
+        }
+        let PickerOutcome { action, items, .. } = match outcome {
+            Some(outcome) => outcome,
             None => return Ok(()),
         };
 
-        let choice = Path::new(&choice);
-        self.execute_selected(choice, &config)
+        let names = match self.unified {
+            true => mux.list_sessions(),
+            false => vec![],
+        };
+        let choice = Item::new(&items[0], &names);
+        if action == Action::OpenWindow {
+            return match choice {
+                Item::Workspace(path) => open_window(&path, &config),
+                Item::Session(name) => Err(miette!(
+                    "Session '{}' can not be opened as a window, only workspaces can",
+                    name
+                )),
+            };
+        }
+
+        let previous = mux.session_name();
+        let kill_config = config.clone();
+        let name = self.attach_item(choice, config)?;
+        if action == Action::AcceptAndKill {
+            kill_previous(&kill_config, previous, &name)?;
+        }
+
+        Ok(())
     }
-}
 
-impl Attach {
-    fn execute_selected(&self, selected: &Path, config: &Config) -> Result<()> {
+    /// Attach to the session of a workspace or to a running session and return its name
+    fn attach_item(&self, item: Item, config: Config) -> Result<String> {
+        match item {
+            Item::Workspace(path) => self.attach_selected(&path, config),
+            Item::Session(name) => {
+                config.mux.attach_session(&name)?;
+                Ok(name)
+            }
+        }
+    }
+
+    fn execute_selected(&self, selected: &Path, config: Config) -> Result<()> {
+        self.attach_selected(selected, config).map(drop)
+    }
+
+    /// Attach to the session of `selected`, creating it if it does not exist, and return its name
+    fn attach_selected(&self, selected: &Path, config: Config) -> Result<String> {
+        // The same directory reached through another path has to resolve to the same session
+        let selected = &selected
+            .canonicalize()
+            .unwrap_or_else(|_| selected.to_owned());
+        let config = config.with_project(selected)?;
+        let mut frecency = Frecency::new()?;
+        frecency.add(selected.display().to_string());
+        frecency.write()?;
+
         let mux = &config.mux;
-        let name = util::format_name(selected.file_name().unwrap().to_str().unwrap());
+        let name = mux.resolve_session_name(&config.session_name.render(selected), selected);
         if mux.session_exists(&name) {
-            return mux.attach_session(&name);
+            mux.attach_session(&name)?;
+            return Ok(name);
         }
 
+        let layout = match self.layout.as_deref() {
+            Some(layout) => Some(
+                config
+                    .layouts
+                    .get(layout)
+                    .ok_or(miette!("Unknown layout: '{}'", layout))?,
+            ),
+            None => config.layouts.get("default"),
+        };
+
         let repo = gix::open(selected).ok();
-        let worktree = self.get_worktree(repo.as_ref(), config);
+        let worktree = self.get_worktree(repo.as_ref(), &config);
         let branch = repo.as_ref().and_then(head_branch);
-        mux.create_session(&name, selected.to_str().unwrap(), branch.as_deref())?;
-
-        if let Some(worktree) = worktree {
-            mux.send_command(&name, &format!("cd {}", worktree.display()))?;
-        }
+        let root = worktree.as_deref().unwrap_or(selected);
+        mux.create_session(&name, root, branch.as_deref(), layout, &config.env)?;
+        mux.set_session_path(&name, selected)?;
 
         mux.attach_session(&name)?;
 
-        Ok(())
+        Ok(name)
     }
 
-    pub fn use_cwd(&self, config: &Config) -> Result<()> {
+    pub fn use_cwd(&self, config: Config) -> Result<()> {
         self.execute_selected(&std::env::current_dir().into_diagnostic()?, config)
     }
 
@@ -146,12 +315,16 @@ impl Attach {
             choices.extend(items);
 
             let choice = Picker::new()
+                .height(config.mode)
+                .finder(&config.finder)
+                .keys(&config.keys)
+                .theme(&config.theme)
                 .items(&choices)
                 .prompt("Worktree: ")
                 .select()
                 .ok()??;
 
-            let choice = Path::new(&choice);
+            let choice = Path::new(&choice.items[0]);
             return worktrees
                 .into_iter()
                 .find(|proxy| proxy.git_dir() == choice)
@@ -168,12 +341,16 @@ impl Attach {
         }
 
         let choice = Picker::new()
+            .height(config.mode)
+            .finder(&config.finder)
+            .keys(&config.keys)
+            .theme(&config.theme)
             .items(&items)
             .prompt("Worktree: ")
             .select()
             .ok()??;
 
-        let choice = Path::new(&choice);
+        let choice = Path::new(&choice.items[0]);
         worktrees
             .into_iter()
             .find(|proxy| proxy.git_dir() == choice)
@@ -181,6 +358,102 @@ impl Attach {
     }
 }
 
+/// Open `selected` as a window of the current session instead of attaching to its session
+fn open_window(selected: &Path, config: &Config) -> Result<()> {
+    let selected = &selected
+        .canonicalize()
+        .unwrap_or_else(|_| selected.to_owned());
+    let mut frecency = Frecency::new()?;
+    frecency.add(selected.display().to_string());
+    frecency.write()?;
+
+    let name = config.session_name.render(selected);
+    config.mux.create_window(&name, Some(selected))
+}
+
+/// Picker handler that kills the given sessions and the running sessions of the given
+/// workspaces. Workspaces stay in the picker. In the unified picker the list is rebuilt from
+/// `workspaces`, which the refresh adds its paths to, so the workspaces of killed sessions
+/// show up again.
+fn kill_item_sessions(
+    mux: Arc<dyn Multiplexer>,
+    workspaces: Option<Arc<Mutex<Vec<String>>>>,
+) -> impl FnMut(&[String]) -> Result<Option<Vec<String>>> {
+    move |items| {
+        let names = match workspaces.is_some() {
+            true => mux.list_sessions(),
+            false => vec![],
+        };
+        for item in items {
+            let name = match Item::new(item, &names) {
+                Item::Workspace(path) => mux.find_session(&path),
+                Item::Session(name) => Some(name),
+            };
+            if let Some(name) = name.filter(|name| mux.session_exists(name)) {
+                mux.kill_session(&name)?;
+            }
+        }
+
+        Ok(workspaces
+            .as_ref()
+            .map(|workspaces| unified_items(&mux.list_session_details(), &lock(workspaces))))
+    }
+}
+
+/// Running sessions shown in the unified picker, none otherwise
+fn list_sessions(mux: &dyn Multiplexer, unified: bool) -> Vec<SessionInfo> {
+    match unified {
+        true => mux.list_session_details(),
+        false => vec![],
+    }
+}
+
+/// Names of the running sessions followed by the workspaces no session was created from
+fn unified_items(sessions: &[SessionInfo], workspaces: &[String]) -> Vec<String> {
+    let opened = session_paths(sessions);
+    sessions
+        .iter()
+        .map(|session| session.name.clone())
+        .chain(
+            workspaces
+                .iter()
+                .filter(|path| !opened.contains(Path::new(path.as_str())))
+                .cloned(),
+        )
+        .collect()
+}
+
+fn lock(workspaces: &Mutex<Vec<String>>) -> MutexGuard<'_, Vec<String>> {
+    workspaces.lock().expect("workspaces lock is not poisoned")
+}
+
+fn session_paths(sessions: &[SessionInfo]) -> HashSet<PathBuf> {
+    sessions
+        .iter()
+        .filter_map(|session| session.path.clone())
+        .collect()
+}
+
+/// Marker, window count and attached flag shown after a session in the unified picker
+fn session_note(session: &SessionInfo) -> String {
+    let windows = match session.windows {
+        1 => "1 window".to_string(),
+        n => format!("{} windows", n),
+    };
+    match session.attached {
+        true => format!("[session, {}, attached]", windows),
+        false => format!("[session, {}]", windows),
+    }
+}
+
+/// Kill the session the picker was opened from after switching to `attached`
+fn kill_previous(config: &Config, previous: Option<String>, attached: &str) -> Result<()> {
+    match previous {
+        Some(previous) if previous != attached => config.mux.kill_session(&previous),
+        _ => Ok(()),
+    }
+}
+
 fn default_branch(repo: &gix::Repository) -> Option<String> {
     let remote = repo
         .find_default_remote(gix::remote::Direction::Fetch)?
@@ -212,3 +485,161 @@ fn is_bare(repo: &gix::Repository) -> bool {
         .boolean("core.bare")
         .unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex, OnceLock},
+    };
+
+    use tempfile::TempDir;
+
+    use crate::{
+        cmd::cli::Attach,
+        config::Config,
+        mux::{FakeMux, Multiplexer},
+    };
+
+    use super::kill_item_sessions;
+
+    /// Workspace directory, also used as the data directory so the frecency file is not written
+    /// to the data directory of the user
+    fn workspace() -> &'static Path {
+        static DIR: OnceLock<TempDir> = OnceLock::new();
+        DIR.get_or_init(|| {
+            let dir = tempfile::tempdir().unwrap();
+            std::env::set_var("TUXMUX_DATA_PATH", dir.path());
+            std::fs::create_dir(dir.path().join("api")).unwrap();
+            dir
+        })
+        .path()
+    }
+
+    fn attach() -> Attach {
+        Attach {
+            exists: false,
+            unified: false,
+            exact: false,
+            default: false,
+            path: None,
+            layout: None,
+            query: None,
+        }
+    }
+
+    fn config(mux: &Arc<FakeMux>) -> Config {
+        Config {
+            mux: mux.clone(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn create_and_attach_session_of_path() {
+        let path = workspace().join("api");
+        let mux = Arc::new(FakeMux::new());
+
+        let attach = Attach {
+            path: Some(path.clone()),
+            ..attach()
+        };
+        attach.execute(config(&mux)).unwrap();
+
+        assert_eq!(mux.session("api").unwrap().path, path);
+        assert_eq!(mux.attached(), ["api"]);
+    }
+
+    #[test]
+    fn attach_running_session_of_path() {
+        let path = workspace().join("api");
+        let mux = Arc::new(FakeMux::new().with_session("api", &path));
+
+        let attach = Attach {
+            path: Some(path),
+            ..attach()
+        };
+        attach.execute(config(&mux)).unwrap();
+
+        assert_eq!(mux.list_sessions(), ["api"]);
+        assert_eq!(mux.attached(), ["api"]);
+    }
+
+    #[test]
+    fn open_same_path_relative_and_absolute() {
+        let path = workspace().join("api");
+        // Relative to the working directory of the test without changing it
+        let cwd = std::env::current_dir().unwrap();
+        let relative = cwd
+            .components()
+            .skip(1)
+            .fold(PathBuf::new(), |relative, _| relative.join(".."))
+            .join(path.strip_prefix("/").unwrap());
+        let mux = Arc::new(FakeMux::new());
+
+        for path in [relative, path.clone()] {
+            let attach = Attach {
+                path: Some(path),
+                ..attach()
+            };
+            attach.execute(config(&mux)).unwrap();
+        }
+
+        assert_eq!(mux.list_sessions(), ["api"]);
+        assert_eq!(mux.session("api").unwrap().path, path);
+        assert_eq!(mux.attached(), ["api", "api"]);
+    }
+
+    #[test]
+    fn fail_on_unknown_layout() {
+        let mux = Arc::new(FakeMux::new());
+
+        let attach = Attach {
+            path: Some(workspace().join("api")),
+            layout: Some("missing".to_string()),
+            ..attach()
+        };
+
+        assert!(attach.execute(config(&mux)).is_err());
+        assert!(mux.attached().is_empty());
+    }
+
+    #[test]
+    fn attach_only_existing_session() {
+        let mux = Arc::new(FakeMux::new().with_session("api", "/src/api"));
+
+        let attach = Attach {
+            exists: true,
+            ..attach()
+        };
+        attach.execute(config(&mux)).unwrap();
+
+        assert_eq!(mux.attached(), ["api"]);
+    }
+
+    #[test]
+    fn kill_sessions_of_items() {
+        let mux = Arc::new(
+            FakeMux::new()
+                .with_session("api", "/src/api")
+                .with_session("web", "/src/web")
+                .with_session("docs", "/src/docs"),
+        );
+        let workspaces = Arc::new(Mutex::new(vec!["/src/api".to_string()]));
+
+        let mut kill = kill_item_sessions(mux.clone(), Some(workspaces.clone()));
+        // Found by the refresh after the picker was opened
+        workspaces.lock().unwrap().push("/src/web".to_string());
+        let items = kill(&["web".to_string(), "/src/api".to_string()]).unwrap();
+
+        assert_eq!(mux.list_sessions(), ["docs"]);
+        assert_eq!(
+            items,
+            Some(vec![
+                "docs".to_string(),
+                "/src/api".to_string(),
+                "/src/web".to_string()
+            ])
+        );
+    }
+}
@@ -23,9 +23,13 @@ By default if no options are passed then the cwd is added to the jump list \
 
 const WCMD_LONG_WINDOW_HELP: &str = "\
 Name of the window to execute the command from. \
-This name window name can be taken from a path. \
-In this case the basename will be used. This is \
-useful with git worktrees and different branches.
+This name window name can be taken from a path, \
+given as an absolute path or one containing '/' \
+such as './api'. In this case the window is named \
+with the 'session_name' template, the basename by \
+default. \
+This is useful with git worktrees and different \
+branches.
 ";
 
 const WCMD_LONG_CMD_HELP: &str = "\
@@ -93,10 +97,14 @@ pub struct Attach {
     #[arg(short, long, default_value_t = false)]
     pub exists: bool,
 
-    // Use exact match search instead of fuzzy
-    // TODO: add exact match to fuzzy searcher
-    // #[arg(short = 'x', long, default_value_t = false)]
-    // pub exact: bool,
+    /// List running sessions together with the workspaces that have no
+    /// session yet
+    #[arg(short, long, default_value_t = false, conflicts_with = "exists")]
+    pub unified: bool,
+
+    /// Use exact match search instead of fuzzy
+    #[arg(short = 'x', long, default_value_t = false)]
+    pub exact: bool,
 
     /// Use the remote's default branch when multiple worktrees are detected
     #[arg(short, long, default_value_t = false)]
@@ -107,6 +115,11 @@ pub struct Attach {
     #[arg(short, long, default_value = None)]
     pub path: Option<PathBuf>,
 
+    /// Name of the layout to apply when creating a new session. Defaults to
+    /// the layout named "default" if one is defined
+    #[arg(short, long, default_value = None)]
+    pub layout: Option<String>,
+
     /// Query to search from. If there is only one result that result will be
     /// automatically selected. If there are multiple results then a search
     /// field will be presented.
@@ -153,7 +166,7 @@ pub struct Jump {
     pub path: Option<PathBuf>,
 }
 
-/// Kill a running tmux session
+/// Kill running tmux sessions. Mark multiple sessions in the picker with tab
 #[derive(Debug, Args)]
 #[command(
     visible_alias("k"),
@@ -32,8 +32,10 @@ impl Run for Jump {
 
         if let Some(index) = self.index {
             if let Some(sel) = list.get(index.saturating_sub(1)) {
-                let name = Path::new(sel).file_name().unwrap().to_str().unwrap();
-                config.mux.create_or_attach(name, sel)?;
+                let path = Path::new(sel);
+                let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+                let name = config.session_name.render(&path);
+                config.mux.create_or_attach(&name, &path)?;
             }
 
             return Ok(());
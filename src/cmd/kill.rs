@@ -1,27 +1,50 @@
-use crate::{cmd::cli::Kill, config::Config, ui::Picker};
+use std::sync::Arc;
+
+use miette::Result;
+
+use crate::{
+    cmd::cli::Kill,
+    config::Config,
+    history::History,
+    mux::Multiplexer,
+    ui::{MatchMode, Picker, SessionPreview},
+};
 
 use super::Run;
 
 impl Run for Kill {
     fn run(self) -> miette::Result<()> {
-        let config = Config::load()?;
+        self.execute(&Config::load()?)
+    }
+}
+
+impl Kill {
+    pub fn execute(self, config: &Config) -> miette::Result<()> {
         let names = config.mux.list_sessions();
         let query = self.query.as_ref().map(|v| v.join(" "));
 
         let selected = if self.all {
             names
         } else {
-            let choice = match Picker::new()
+            Picker::new()
+                .height(config.mode)
+                .finder(&config.finder)
+                .keys(&config.keys)
+                .theme(&config.theme)
+                .history(History::new()?)
+                .match_mode(match self.exact {
+                    true => MatchMode::Exact,
+                    false => MatchMode::Fuzzy,
+                })
                 .items(&names)
+                .preview(SessionPreview::new(config.mux.clone()), &config.preview)
+                .on_kill(kill_sessions(config.mux.clone()))
+                .on_rename(rename_session(config.mux.clone()))
                 .filter(query.as_deref())
                 .prompt("> ")
-                .select()?
-            {
-                Some(s) => s,
-                None => return Ok(()),
-            };
-
-            vec![choice]
+                .select_many()?
+                .map(|outcome| outcome.items)
+                .unwrap_or_default()
         };
 
         for sel in selected {
@@ -32,3 +55,55 @@ impl Run for Kill {
         Ok(())
     }
 }
+
+/// Picker handler that kills the given sessions and lists the remaining ones
+pub fn kill_sessions(
+    mux: Arc<dyn Multiplexer>,
+) -> impl FnMut(&[String]) -> Result<Option<Vec<String>>> {
+    move |names| {
+        for name in names {
+            mux.kill_session(name)?;
+        }
+        Ok(Some(mux.list_sessions()))
+    }
+}
+
+/// Picker handler that renames a session and lists the sessions with the new name
+pub fn rename_session(
+    mux: Arc<dyn Multiplexer>,
+) -> impl FnMut(&str, &str) -> Result<Option<Vec<String>>> {
+    move |name, new_name| {
+        mux.rename_session(name, new_name)?;
+        Ok(Some(mux.list_sessions()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{cmd::cli::Kill, config::Config, mux::FakeMux};
+
+    #[test]
+    fn kill_all_sessions() {
+        let mux = Arc::new(
+            FakeMux::new()
+                .with_session("api", "/src/api")
+                .with_session("web", "/src/web"),
+        );
+        let config = Config {
+            mux: mux.clone(),
+            ..Config::default()
+        };
+
+        let kill = Kill {
+            all: true,
+            exact: false,
+            query: None,
+        };
+        kill.execute(&config).unwrap();
+
+        assert!(mux.session("api").is_none());
+        assert!(mux.session("web").is_none());
+    }
+}
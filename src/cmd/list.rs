@@ -1,4 +1,4 @@
-use crate::{cmd::cli::List, config::Config, walker::Walker};
+use crate::{cmd::cli::List, config::Config, index::Index, walker::Walker};
 
 use super::Run;
 
@@ -6,9 +6,12 @@ impl Run for List {
     fn run(self) -> miette::Result<()> {
         let config = Config::load()?;
         if self.all {
-            for path in config.paths_from_walk() {
+            let mut index = Index::default();
+            config.walk(|path| {
                 println!("{}", path);
-            }
+                index.0.push(path);
+            });
+            index.write()?;
 
             return Ok(());
         }
@@ -2,19 +2,31 @@ use std::path::Path;
 
 use miette::miette;
 
-use crate::{cmd::cli::Wcmd, config::Config, util::intersperse};
+use crate::{
+    cmd::cli::Wcmd,
+    config::Config,
+    util::{self, intersperse},
+};
 
 use super::Run;
 
 impl Run for Wcmd {
     fn run(self) -> miette::Result<()> {
-        let config = Config::load()?;
-        let mux = config.mux;
-        let name = Path::new(&self.window)
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap();
+        self.execute(&Config::load()?)
+    }
+}
+
+impl Wcmd {
+    pub fn execute(self, config: &Config) -> miette::Result<()> {
+        let mux = &config.mux;
+        // Windows given as the path of a workspace are named like its session
+        let path = Path::new(&self.window);
+        let name = match path.is_absolute() || self.window.contains('/') {
+            true => config
+                .session_name
+                .render(&std::fs::canonicalize(path).unwrap_or(path.to_owned())),
+            false => util::format_name(&self.window),
+        };
 
         let session_name = mux
             .session_name()
@@ -23,7 +35,7 @@ impl Run for Wcmd {
         let target = format!("{}:{}", session_name.trim(), name);
 
         if !mux.session_exists(&target) {
-            mux.create_window(name)?;
+            mux.create_window(&name, None)?;
         }
 
         let cmd: String = intersperse(self.cmds.iter().map(|f| f.as_str()), " ").collect();
@@ -32,3 +44,88 @@ impl Run for Wcmd {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{cmd::cli::Wcmd, config::Config, mux::FakeMux};
+
+    fn config(mux: &Arc<FakeMux>) -> Config {
+        Config {
+            mux: mux.clone(),
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn create_window_and_send_command() {
+        let mux = Arc::new(
+            FakeMux::new()
+                .with_session("api", "/src/api")
+                .with_current("api"),
+        );
+
+        let wcmd = Wcmd {
+            window: "build".to_string(),
+            cmds: vec!["cargo".to_string(), "build".to_string()],
+        };
+        wcmd.execute(&config(&mux)).unwrap();
+
+        assert_eq!(mux.session("api").unwrap().windows, ["0", "build"]);
+        assert_eq!(
+            mux.commands(),
+            [("api:build".to_string(), "cargo build".to_string())]
+        );
+    }
+
+    #[test]
+    fn reuse_existing_window() {
+        let mux = Arc::new(
+            FakeMux::new()
+                .with_session("api", "/src/api")
+                .with_current("api"),
+        );
+
+        for _ in 0..2 {
+            let wcmd = Wcmd {
+                window: "build".to_string(),
+                cmds: vec!["make".to_string()],
+            };
+            wcmd.execute(&config(&mux)).unwrap();
+        }
+
+        assert_eq!(mux.session("api").unwrap().windows, ["0", "build"]);
+        assert_eq!(mux.commands().len(), 2);
+    }
+
+    #[test]
+    fn name_window_of_path() {
+        let mux = Arc::new(
+            FakeMux::new()
+                .with_session("api", "/src/api")
+                .with_current("api"),
+        );
+
+        for window in ["/src/oss/web", "oss/web"] {
+            let wcmd = Wcmd {
+                window: window.to_string(),
+                cmds: vec!["make".to_string()],
+            };
+            wcmd.execute(&config(&mux)).unwrap();
+        }
+
+        assert_eq!(mux.session("api").unwrap().windows, ["0", "web"]);
+    }
+
+    #[test]
+    fn fail_outside_of_session() {
+        let mux = Arc::new(FakeMux::new().with_session("api", "/src/api"));
+
+        let wcmd = Wcmd {
+            window: "build".to_string(),
+            cmds: vec!["make".to_string()],
+        };
+        assert!(wcmd.execute(&config(&mux)).is_err());
+    }
+}
@@ -68,13 +68,82 @@ pub enum ParseError {
         #[label("expected child nodes")] SourceSpan,
     ),
 
+    #[error("Duplicate layout")]
+    #[diagnostic(code("tm::duplicate_layout"))]
+    DuplicateLayout(
+        /// Name of the layout
+        String,
+        #[source_code] Source,
+        #[label("layout '{0}' is already defined")] SourceSpan,
+    ),
+
+    #[error("Invalid color")]
+    #[diagnostic(
+        code("tm::invalid_color"),
+        help("colors are names such as 'blue' or 'light-red', 'default', a 256 color index or a hex value such as '#ff8700'")
+    )]
+    InvalidColor(
+        #[source_code] Source,
+        #[label("Not a valid color")] SourceSpan,
+    ),
+
+    #[error("Invalid depth")]
+    #[diagnostic(code("tm::invalid_depth"))]
+    InvalidDepth(
+        #[source_code] Source,
+        #[label("expected a depth of 0 or more")] SourceSpan,
+    ),
+
+    #[error("Invalid exclude path")]
+    #[diagnostic(
+        code("tm::invalid_exclude_path"),
+        help("rules containing '/' are matched against absolute paths and have to start with '/', '~' or '**'")
+    )]
+    InvalidExcludePath(
+        #[source_code] Source,
+        #[label("relative paths never match")] SourceSpan,
+    ),
+
     #[error("Invalid finder")]
-    #[diagnostic(code("tm::invalid_finder"))]
+    #[diagnostic(
+        code("tm::invalid_finder"),
+        help("valid values are 'builtin', 'fzf', 'sk' or a command line")
+    )]
     InvalidFinder(
         #[source_code] Source,
         #[label("Not a valid finder")] SourceSpan,
     ),
 
+    #[error("Invalid height")]
+    #[diagnostic(
+        code("tm::invalid_height"),
+        help("valid values are 'full', a percentage such as '40%' or a number of lines")
+    )]
+    InvalidHeight(
+        #[source_code] Source,
+        #[label("Not a valid height")] SourceSpan,
+    ),
+
+    #[error("Invalid key chord")]
+    #[diagnostic(
+        code("tm::invalid_key_chord"),
+        help("chords are modifiers 'ctrl', 'alt' or 'shift' followed by a key such as 'ctrl-p', 'alt-enter' or 'f2'")
+    )]
+    InvalidKeyChord(
+        #[source_code] Source,
+        #[label("Not a valid key chord")] SourceSpan,
+    ),
+
+    #[error("Invalid multiplexer")]
+    #[diagnostic(
+        code("tm::invalid_multiplexer"),
+        help("valid values are the registered multiplexers such as 'tmux' or 'zellij'")
+    )]
+    InvalidMultiplexer(
+        #[source_code] Source,
+        #[label("Not a valid multiplexer")] SourceSpan,
+    ),
+
     #[error("Invalid percentage")]
     #[diagnostic(code("tm::invalid_percentage"))]
     InvalidPercentage(
@@ -82,6 +151,47 @@ pub enum ParseError {
         #[label("expected a percentage from 1-100%")] SourceSpan,
     ),
 
+    #[error("Invalid preview position")]
+    #[diagnostic(
+        code("tm::invalid_preview_position"),
+        help("valid values are 'right' or 'top'")
+    )]
+    InvalidPreviewPosition(
+        #[source_code] Source,
+        #[label("Not a valid preview position")] SourceSpan,
+    ),
+
+    #[error("Invalid session name")]
+    #[diagnostic(
+        code("tm::invalid_session_name"),
+        help("session names are text with placeholders in braces, valid placeholders are {0}")
+    )]
+    InvalidSessionName(
+        /// The valid placeholders
+        String,
+        #[source_code] Source,
+        #[label("Not a valid session name template")] SourceSpan,
+    ),
+
+    #[error("Invalid split")]
+    #[diagnostic(
+        code("tm::invalid_split"),
+        help("valid values are 'horizontal' or 'vertical'")
+    )]
+    InvalidSplit(
+        #[source_code] Source,
+        #[label("Not a valid split direction")] SourceSpan,
+    ),
+
+    #[error("Unknown action")]
+    #[diagnostic(code("tm::unknown_action"), help("valid actions are {0}"))]
+    UnknownAction(
+        /// Names of the valid actions
+        String,
+        #[source_code] Source,
+        #[label("Not a valid action")] SourceSpan,
+    ),
+
     #[error("Unknown configuration option")]
     #[diagnostic(code("tm::unknown_configuration_option"))]
     UnknownConfigurationOption(
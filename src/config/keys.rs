@@ -0,0 +1,393 @@
+use std::{fmt, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use indexmap::IndexMap;
+
+/// Something the picker does in response to a key chord.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    BackwardChar,
+    ForwardChar,
+    BackwardWord,
+    ForwardWord,
+    BeginningOfLine,
+    EndOfLine,
+    BackwardKillWord,
+    KillWord,
+    /// Delete the query before the cursor
+    BackwardKillLine,
+    /// Delete the query after the cursor
+    KillLine,
+    PreviousHistory,
+    NextHistory,
+    TogglePreview,
+    /// Mark the item under the cursor and move to the next item
+    ToggleSelect,
+    /// Mark the item under the cursor and move to the previous item
+    ToggleSelectPrevious,
+    CycleMatchMode,
+    Accept,
+    /// Accept the item under the cursor and kill the session the picker was opened from
+    AcceptAndKill,
+    /// Kill the session of the marked items or the item under the cursor and keep picking
+    Kill,
+    /// Rename the session under the cursor and keep picking
+    Rename,
+    /// Open the item under the cursor as a window of the current session
+    OpenWindow,
+    Cancel,
+}
+
+impl Action {
+    pub const ALL: [Action; 28] = [
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::First,
+        Action::Last,
+        Action::BackwardChar,
+        Action::ForwardChar,
+        Action::BackwardWord,
+        Action::ForwardWord,
+        Action::BeginningOfLine,
+        Action::EndOfLine,
+        Action::BackwardKillWord,
+        Action::KillWord,
+        Action::BackwardKillLine,
+        Action::KillLine,
+        Action::PreviousHistory,
+        Action::NextHistory,
+        Action::TogglePreview,
+        Action::ToggleSelect,
+        Action::ToggleSelectPrevious,
+        Action::CycleMatchMode,
+        Action::Accept,
+        Action::AcceptAndKill,
+        Action::Kill,
+        Action::Rename,
+        Action::OpenWindow,
+        Action::Cancel,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page-up",
+            Action::PageDown => "page-down",
+            Action::First => "first",
+            Action::Last => "last",
+            Action::BackwardChar => "backward-char",
+            Action::ForwardChar => "forward-char",
+            Action::BackwardWord => "backward-word",
+            Action::ForwardWord => "forward-word",
+            Action::BeginningOfLine => "beginning-of-line",
+            Action::EndOfLine => "end-of-line",
+            Action::BackwardKillWord => "backward-kill-word",
+            Action::KillWord => "kill-word",
+            Action::BackwardKillLine => "backward-kill-line",
+            Action::KillLine => "kill-line",
+            Action::PreviousHistory => "previous-history",
+            Action::NextHistory => "next-history",
+            Action::TogglePreview => "toggle-preview",
+            Action::ToggleSelect => "toggle-select",
+            Action::ToggleSelectPrevious => "toggle-select-previous",
+            Action::CycleMatchMode => "cycle-match-mode",
+            Action::Accept => "accept",
+            Action::AcceptAndKill => "accept-and-kill",
+            Action::Kill => "kill",
+            Action::Rename => "rename",
+            Action::OpenWindow => "open-window",
+            Action::Cancel => "cancel",
+        }
+    }
+
+    /// Actions that move the cursor in the query or delete part of it
+    pub fn edits_query(self) -> bool {
+        matches!(
+            self,
+            Action::BackwardChar
+                | Action::ForwardChar
+                | Action::BackwardWord
+                | Action::ForwardWord
+                | Action::BeginningOfLine
+                | Action::EndOfLine
+                | Action::BackwardKillWord
+                | Action::KillWord
+                | Action::BackwardKillLine
+                | Action::KillLine
+        )
+    }
+}
+
+impl FromStr for Action {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or(())
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A key with its modifiers such as `ctrl-p`, `alt-enter` or `shift-tab`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }.normalize()
+    }
+
+    /// Shift is part of the character for printable keys and of `BackTab`, so it is dropped to
+    /// make chords compare the same no matter how the terminal reports them. Terminals can not
+    /// tell `ctrl-p` and `ctrl-P` apart.
+    fn normalize(mut self) -> Self {
+        if matches!(self.code, KeyCode::Char(_) | KeyCode::BackTab) {
+            self.modifiers.remove(KeyModifiers::SHIFT);
+        }
+        if let KeyCode::Char(c) = self.code {
+            if self.modifiers.contains(KeyModifiers::CONTROL) {
+                self.code = KeyCode::Char(c.to_ascii_lowercase());
+            }
+        }
+        if self.code == KeyCode::Tab && self.modifiers.contains(KeyModifiers::SHIFT) {
+            self.code = KeyCode::BackTab;
+            self.modifiers.remove(KeyModifiers::SHIFT);
+        }
+        self
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = ();
+
+    /// Parse a chord of `-` separated modifiers followed by a key name or a single character.
+    /// Both `ctrl--` and `-` are the minus key.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = match s.rsplit_once('-') {
+            Some(("", "")) => ("", "-"),
+            Some((modifiers, "")) => (modifiers.strip_suffix('-').ok_or(())?, "-"),
+            Some((modifiers, key)) => (modifiers, key),
+            None => ("", s),
+        };
+
+        let mut chord_modifiers = KeyModifiers::NONE;
+        for modifier in modifiers.split('-').filter(|_| !modifiers.is_empty()) {
+            chord_modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "c" => KeyModifiers::CONTROL,
+                "alt" | "a" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => return Err(()),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" | "bspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').map(str::parse) {
+                    Some(Ok(n @ 1..=24)) => KeyCode::F(n),
+                    _ => return Err(()),
+                },
+            },
+        };
+
+        Ok(Self::new(code, chord_modifiers))
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (KeyModifiers::CONTROL, "ctrl-"),
+            (KeyModifiers::ALT, "alt-"),
+            (KeyModifiers::SHIFT, "shift-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}", name)?;
+            }
+        }
+
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::BackTab => write!(f, "shift-tab"),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+/// Key chords bound to picker actions. Keys that are not bound edit the query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keys(pub IndexMap<KeyChord, Action>);
+
+impl Keys {
+    pub fn get(&self, event: KeyEvent) -> Option<Action> {
+        self.0.get(&KeyChord::from(event)).copied()
+    }
+
+    pub fn bind(&mut self, chord: KeyChord, action: Action) {
+        self.0.insert(chord, action);
+    }
+
+    /// Remove the binding of `chord` so it edits the query again
+    pub fn unbind(&mut self, chord: &KeyChord) {
+        self.0.shift_remove(chord);
+    }
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        let ctrl = |c| KeyChord::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+        let alt = |c| KeyChord::new(KeyCode::Char(c), KeyModifiers::ALT);
+        let key = |code| KeyChord::new(code, KeyModifiers::NONE);
+
+        Self(IndexMap::from([
+            (key(KeyCode::Up), Action::Up),
+            (ctrl('p'), Action::Up),
+            (key(KeyCode::Down), Action::Down),
+            (ctrl('n'), Action::Down),
+            (key(KeyCode::PageUp), Action::PageUp),
+            (key(KeyCode::PageDown), Action::PageDown),
+            (key(KeyCode::Left), Action::BackwardChar),
+            (ctrl('b'), Action::BackwardChar),
+            (ctrl('h'), Action::BackwardChar),
+            (key(KeyCode::Right), Action::ForwardChar),
+            (ctrl('f'), Action::ForwardChar),
+            (ctrl('l'), Action::ForwardChar),
+            (alt('b'), Action::BackwardWord),
+            (alt('f'), Action::ForwardWord),
+            (key(KeyCode::Home), Action::BeginningOfLine),
+            (ctrl('a'), Action::BeginningOfLine),
+            (key(KeyCode::End), Action::EndOfLine),
+            (ctrl('e'), Action::EndOfLine),
+            (ctrl('w'), Action::BackwardKillWord),
+            (alt('d'), Action::KillWord),
+            (ctrl('u'), Action::BackwardKillLine),
+            (ctrl('k'), Action::KillLine),
+            (alt('p'), Action::PreviousHistory),
+            (alt('n'), Action::NextHistory),
+            (ctrl('t'), Action::TogglePreview),
+            (key(KeyCode::Tab), Action::ToggleSelect),
+            (key(KeyCode::BackTab), Action::ToggleSelectPrevious),
+            (alt('r'), Action::CycleMatchMode),
+            (key(KeyCode::Enter), Action::Accept),
+            (ctrl('x'), Action::Kill),
+            (ctrl('r'), Action::Rename),
+            (ctrl('o'), Action::OpenWindow),
+            (key(KeyCode::Esc), Action::Cancel),
+            (ctrl('c'), Action::Cancel),
+            (ctrl('d'), Action::Cancel),
+            (ctrl('z'), Action::Cancel),
+        ]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    use super::KeyChord;
+
+    fn parse(s: &str) -> KeyChord {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trip_chords() {
+        for chord in [
+            "ctrl-p",
+            "alt-enter",
+            "ctrl-alt-x",
+            "space",
+            "f12",
+            "-",
+            "ctrl--",
+        ] {
+            assert_eq!(parse(chord).to_string(), chord);
+        }
+    }
+
+    #[test]
+    fn parse_minus_key() {
+        assert_eq!(
+            parse("-"),
+            KeyChord::new(KeyCode::Char('-'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            parse("ctrl--"),
+            KeyChord::new(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+        assert!("ctrl-".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn parse_shift_tab_as_backtab() {
+        let backtab = KeyChord::new(KeyCode::BackTab, KeyModifiers::NONE);
+        assert_eq!(parse("shift-tab"), backtab);
+        assert_eq!(parse("backtab"), backtab);
+        assert_eq!(backtab.to_string(), "shift-tab");
+        let event = KeyEvent::new(KeyCode::BackTab, KeyModifiers::SHIFT);
+        assert_eq!(KeyChord::from(event), backtab);
+    }
+
+    #[test]
+    fn ignore_case_of_control_chords() {
+        assert_eq!(parse("ctrl-P"), parse("ctrl-p"));
+        assert_eq!(parse("CTRL-p"), parse("ctrl-p"));
+        let event = KeyEvent::new(
+            KeyCode::Char('P'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        assert_eq!(KeyChord::from(event), parse("ctrl-p"));
+        assert_ne!(parse("P"), parse("p"));
+    }
+
+    #[test]
+    fn reject_invalid_chords() {
+        for chord in ["hyper-p", "f25", "enterr", ""] {
+            assert!(chord.parse::<KeyChord>().is_err(), "{}", chord);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+/// Direction a pane is split from the previous pane in the window.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    /// Place the new pane below the previous one
+    #[default]
+    Vertical,
+    /// Place the new pane to the right of the previous one
+    Horizontal,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Pane {
+    pub command: Option<String>,
+    pub split: Split,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Window {
+    pub name: Option<String>,
+    pub panes: Vec<Pane>,
+}
+
+/// A set of windows and panes that are created when a new session is started.
+#[derive(Debug, Default, Clone)]
+pub struct Layout {
+    pub name: String,
+    pub windows: Vec<Window>,
+}
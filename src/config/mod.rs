@@ -1,17 +1,54 @@
-use crate::{mux::Mux, util};
-use indexmap::{indexset, IndexSet};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    mux::{self, Multiplexer},
+    util,
+};
+use indexmap::{indexset, IndexMap, IndexSet};
 
 mod error;
+mod keys;
+mod layout;
 mod parser;
+mod preview;
+mod session_name;
 mod source;
+mod theme;
 
 pub use error::ParseError;
+pub use keys::{Action, KeyChord, Keys};
+pub use layout::{Layout, Pane, Split, Window};
 pub use parser::Parser;
+pub use preview::{Preview, PreviewPosition};
+pub use session_name::{Placeholder, SessionName};
 pub use source::Source;
+pub use theme::Theme;
+
+/// Project configuration files searched for in the root of a workspace, in order of precedence.
+const PROJECT_CONFIG_FILES: [&str; 2] = [".tux.kdl", ".config/tux.kdl"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    pub path: String,
+    /// Overrides the global search depth for this workspace
+    pub depth: Option<usize>,
+}
 
-#[derive(Debug)]
+impl Workspace {
+    pub fn new<S: Into<String>>(path: S) -> Self {
+        Self {
+            path: path.into(),
+            depth: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SearchPath {
-    pub workspace: Vec<String>,
+    pub workspace: Vec<Workspace>,
     pub single: Vec<String>,
 }
 
@@ -28,20 +65,46 @@ impl Default for Mode {
     }
 }
 
-#[derive(Debug)]
+/// Fuzzy finder used to pick from a list of items.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Finder {
+    /// Built-in picker
+    #[default]
+    Builtin,
+    Fzf,
+    Skim,
+    /// Command line run with `sh -c`. Items are written to its stdin one per line and the
+    /// selected items are read from its stdout.
+    Command(String),
+}
+
+#[derive(Debug, Clone)]
 pub struct Config {
     pub search: SearchPath,
     pub exclude_path: IndexSet<String>,
+    /// Workspaces whose project configuration is loaded. Project configurations can run
+    /// commands so they are ignored unless the workspace is in or below one of these paths.
+    pub trusted_projects: Vec<PathBuf>,
+    pub markers: IndexSet<String>,
     pub depth: usize,
     pub mode: Mode,
+    pub finder: Finder,
     pub default_worktree: bool,
-    pub mux: Mux,
+    pub respect_ignore: bool,
+    pub layouts: IndexMap<String, Layout>,
+    pub env: IndexMap<String, String>,
+    pub preview: Preview,
+    pub keys: Keys,
+    pub theme: Theme,
+    /// Template of the names of new sessions
+    pub session_name: SessionName,
+    pub mux: Arc<dyn Multiplexer>,
 }
 
 impl Default for SearchPath {
     fn default() -> Self {
         Self {
-            workspace: vec![shellexpand::tilde("~").to_string()],
+            workspace: vec![Workspace::new(shellexpand::tilde("~"))],
             single: vec![],
         }
     }
@@ -52,10 +115,20 @@ impl Default for Config {
         Self {
             search: SearchPath::default(),
             exclude_path: indexset! { "node_modules".to_string(), ".direnv".to_string(), ".cache".to_string(), ".local".to_string()},
+            trusted_projects: vec![],
+            markers: IndexSet::new(),
             depth: 5,
             mode: Mode::default(),
+            finder: Finder::default(),
             default_worktree: false,
-            mux: Mux::default(),
+            respect_ignore: false,
+            layouts: IndexMap::new(),
+            env: IndexMap::new(),
+            preview: Preview::default(),
+            keys: Keys::default(),
+            theme: Theme::default(),
+            session_name: SessionName::default(),
+            mux: mux::detect(),
         }
     }
 }
@@ -78,4 +151,47 @@ impl Config {
 
         Ok(config)
     }
+
+    /// Layer the project configuration file found in `path` on top of this config. If the
+    /// project does not contain a configuration file or is not trusted the config is returned
+    /// unchanged.
+    pub fn with_project<P: AsRef<Path>>(self, path: P) -> Result<Config, ParseError> {
+        let Some(project_path) = Self::project_path(path.as_ref()) else {
+            return Ok(self);
+        };
+
+        if !self.is_trusted(path.as_ref()) {
+            eprintln!(
+                "Ignoring untrusted project configuration '{}'. Add the workspace to \
+                 'trusted_projects' to load it.",
+                project_path.display()
+            );
+            return Ok(self);
+        }
+
+        // A project can not extend the list of trusted projects
+        let trusted_projects = self.trusted_projects.clone();
+        let mut config = Parser::new(Source::load(project_path.display().to_string())?)
+            .parse_with_config(Some(self))?;
+        config.trusted_projects = trusted_projects;
+        Ok(config)
+    }
+
+    /// The workspace at `path` is in or below one of the trusted project paths
+    pub fn is_trusted(&self, path: &Path) -> bool {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        self.trusted_projects.iter().any(|trusted| {
+            let trusted = trusted
+                .canonicalize()
+                .unwrap_or_else(|_| trusted.to_owned());
+            path.starts_with(trusted)
+        })
+    }
+
+    pub fn project_path(path: &Path) -> Option<PathBuf> {
+        PROJECT_CONFIG_FILES
+            .iter()
+            .map(|file| path.join(file))
+            .find(|file| file.is_file())
+    }
 }
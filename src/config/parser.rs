@@ -1,7 +1,21 @@
+use std::{collections::HashSet, path::PathBuf};
+
 use itertools::Itertools;
 use kdl::{KdlDocument, KdlEntry, KdlNode, KdlValue};
+use ratatui::style::Color;
+
+use crate::mux;
 
-use super::{error::ParseError, source::Source, Config};
+use super::{
+    error::ParseError,
+    keys::{Action, KeyChord, Keys},
+    layout::{Layout, Pane, Split, Window},
+    preview::{Preview, PreviewPosition},
+    session_name::Placeholder,
+    source::Source,
+    theme::Theme,
+    Config, Finder, Mode, Workspace,
+};
 
 #[derive(Debug)]
 pub struct Parser {
@@ -19,13 +33,20 @@ fn type_from_value(value: &KdlValue) -> &'static str {
 }
 
 fn get_dash_values<'a>(doc: &'a KdlDocument, name: &'static str) -> Vec<&'a KdlValue> {
+    get_dash_entries(doc, name)
+        .into_iter()
+        .map(|e| e.value())
+        .collect_vec()
+}
+
+fn get_dash_entries<'a>(doc: &'a KdlDocument, name: &'static str) -> Vec<&'a KdlEntry> {
     doc.get(name)
         .and_then(|n| n.children())
         .map(|doc| doc.nodes())
         .unwrap_or_default()
         .iter()
         .filter(|e| e.name().value() == "-")
-        .filter_map(|n| n.get(0))
+        .filter_map(|n| n.entry(0))
         .collect_vec()
 }
 
@@ -44,6 +65,7 @@ impl Parser {
 
     fn inner_parse(self, mut config: Config) -> Result<Config, ParseError> {
         let doc: KdlDocument = self.src.raw.parse()?;
+        let mut layout_names = HashSet::new();
 
         for node in doc.nodes() {
             match node.name().value() {
@@ -51,8 +73,7 @@ impl Parser {
                     if let Some(doc) = node.children() {
                         if let Some(workspace_node) = doc.get("workspace") {
                             let default = self.get_default_optional(workspace_node)?;
-                            let mut workspaces =
-                                self.try_get_dash_values_as_valid_paths(doc, "workspace")?;
+                            let mut workspaces = self.try_get_workspaces(doc)?;
 
                             if default {
                                 config.search.workspace.append(&mut workspaces);
@@ -77,6 +98,7 @@ impl Parser {
                 "exclude_path" => {
                     let default = self.get_default_optional(node)?;
                     let paths = self.try_get_dash_values_as_string(&doc, "exclude_path")?;
+                    self.check_exclude_paths(&doc)?;
 
                     if default {
                         config.exclude_path.extend(paths);
@@ -84,12 +106,93 @@ impl Parser {
                         config.exclude_path = paths.into_iter().collect();
                     }
                 }
+                "trusted_projects" => {
+                    let paths =
+                        self.try_get_dash_values_as_valid_paths(&doc, "trusted_projects")?;
+                    config
+                        .trusted_projects
+                        .extend(paths.into_iter().map(PathBuf::from));
+                }
+                "markers" => {
+                    let markers = self.try_get_dash_values_as_string(&doc, "markers")?;
+                    config.markers.extend(markers);
+                }
                 "depth" => {
-                    config.depth = usize::try_from(self.first_entry_as_i64(node)?).unwrap_or(0);
+                    config.depth = self.entry_as_depth(self.first_entry(node)?)?;
+                }
+                "height" => {
+                    config.mode = self.parse_height(node)?;
+                }
+                "finder" => {
+                    let entry = self.first_entry(node)?;
+                    config.finder = match entry.value().as_string() {
+                        Some("builtin") => Finder::Builtin,
+                        Some("fzf") => Finder::Fzf,
+                        Some("sk") => Finder::Skim,
+                        Some(command) if !command.trim().is_empty() => {
+                            Finder::Command(command.to_string())
+                        }
+                        _ => return Err(ParseError::InvalidFinder(self.src.clone(), entry.span())),
+                    };
                 }
                 "default_worktree" => {
                     config.default_worktree = self.first_entry_as_bool(node).unwrap_or(false);
                 }
+                "respect_ignore" => {
+                    config.respect_ignore = self.first_entry_as_bool(node)?;
+                }
+                "env" => {
+                    let doc = node
+                        .children()
+                        .ok_or(ParseError::MissingChildNode(self.src.clone(), node.span()))?;
+                    for var in doc.nodes() {
+                        let value = self.first_entry_as_string(var)?.to_string();
+                        config.env.insert(var.name().value().to_string(), value);
+                    }
+                }
+                "multiplexer" => {
+                    let entry = self.first_entry(node)?;
+                    config.mux = entry.value().as_string().and_then(mux::create).ok_or(
+                        ParseError::InvalidMultiplexer(self.src.clone(), entry.span()),
+                    )?;
+                }
+                "preview" => {
+                    config.preview = self.parse_preview(node, config.preview)?;
+                }
+                "session_name" => {
+                    let entry = self.first_entry(node)?;
+                    config.session_name = entry
+                        .value()
+                        .as_string()
+                        .and_then(|template| template.parse().ok())
+                        .ok_or_else(|| {
+                            ParseError::InvalidSessionName(
+                                Placeholder::ALL.iter().join(", "),
+                                self.src.clone(),
+                                entry.span(),
+                            )
+                        })?;
+                }
+                "theme" => {
+                    config.theme = self.parse_theme(node, config.theme)?;
+                }
+                "keys" => {
+                    self.parse_keys(node, &mut config.keys)?;
+                }
+                "layout" => {
+                    let layout = self.parse_layout(node)?;
+                    // Layouts of an earlier file such as the global config can be replaced by a
+                    // later one, but a name may only be defined once per file
+                    if !layout_names.insert(layout.name.clone()) {
+                        let span = node.entry(0).map_or(node.span(), |entry| entry.span());
+                        return Err(ParseError::DuplicateLayout(
+                            layout.name,
+                            self.src.clone(),
+                            span,
+                        ));
+                    }
+                    config.layouts.insert(layout.name.clone(), layout);
+                }
                 option => {
                     return Err(ParseError::UnknownConfigurationOption(
                         option.to_owned(),
@@ -103,6 +206,209 @@ impl Parser {
         Ok(config)
     }
 
+    fn parse_layout(&self, node: &KdlNode) -> Result<Layout, ParseError> {
+        let name = self.first_entry_as_string(node)?.to_string();
+        let doc = node
+            .children()
+            .ok_or(ParseError::MissingChildNode(self.src.clone(), node.span()))?;
+
+        let windows = doc
+            .nodes()
+            .iter()
+            .map(|child| match child.name().value() {
+                "window" => self.parse_window(child),
+                other => Err(ParseError::NodeMismatch(
+                    "window",
+                    other.to_owned(),
+                    self.src.clone(),
+                    child.name().span(),
+                )),
+            })
+            .try_collect()?;
+
+        Ok(Layout { name, windows })
+    }
+
+    fn parse_window(&self, node: &KdlNode) -> Result<Window, ParseError> {
+        let name = self.optional_entry_as_string(node, 0)?;
+        let panes = match node.children() {
+            Some(doc) => doc
+                .nodes()
+                .iter()
+                .map(|child| match child.name().value() {
+                    "pane" => self.parse_pane(child),
+                    other => Err(ParseError::NodeMismatch(
+                        "pane",
+                        other.to_owned(),
+                        self.src.clone(),
+                        child.name().span(),
+                    )),
+                })
+                .try_collect()?,
+            None => vec![],
+        };
+
+        Ok(Window { name, panes })
+    }
+
+    fn parse_pane(&self, node: &KdlNode) -> Result<Pane, ParseError> {
+        let command = self.optional_entry_as_string(node, 0)?;
+        let split = match node.entry("split") {
+            Some(entry) => match entry.value().as_string() {
+                Some("horizontal") => Split::Horizontal,
+                Some("vertical") => Split::Vertical,
+                _ => return Err(ParseError::InvalidSplit(self.src.clone(), entry.span())),
+            },
+            None => Split::default(),
+        };
+
+        Ok(Pane { command, split })
+    }
+
+    fn parse_height(&self, node: &KdlNode) -> Result<Mode, ParseError> {
+        let entry = self.first_entry(node)?;
+        match entry.value() {
+            KdlValue::String(s) if s == "full" => Ok(Mode::Full),
+            KdlValue::String(s) => match s.strip_suffix('%').map(|p| p.trim().parse::<f32>()) {
+                Some(Ok(percentage)) if percentage > 0.0 && percentage <= 100.0 => {
+                    Ok(Mode::Percentage(percentage / 100.0))
+                }
+                Some(_) => Err(ParseError::InvalidPercentage(
+                    self.src.clone(),
+                    entry.span(),
+                )),
+                None => Err(ParseError::InvalidHeight(self.src.clone(), entry.span())),
+            },
+            KdlValue::Integer(lines) => u16::try_from(*lines)
+                .ok()
+                .filter(|lines| *lines > 0)
+                .map(Mode::Lines)
+                .ok_or(ParseError::InvalidHeight(self.src.clone(), entry.span())),
+            _ => Err(ParseError::InvalidHeight(self.src.clone(), entry.span())),
+        }
+    }
+
+    fn parse_preview(&self, node: &KdlNode, mut preview: Preview) -> Result<Preview, ParseError> {
+        let doc = node
+            .children()
+            .ok_or(ParseError::MissingChildNode(self.src.clone(), node.span()))?;
+
+        for child in doc.nodes() {
+            match child.name().value() {
+                "position" => {
+                    let entry = self.first_entry(child)?;
+                    preview.position = match entry.value().as_string() {
+                        Some("right") => PreviewPosition::Right,
+                        Some("top") => PreviewPosition::Top,
+                        _ => {
+                            return Err(ParseError::InvalidPreviewPosition(
+                                self.src.clone(),
+                                entry.span(),
+                            ))
+                        }
+                    };
+                }
+                "size" => {
+                    preview.size = match self.first_entry_as_i64(child)? {
+                        size @ 1..=100 => size as u16,
+                        _ => {
+                            return Err(ParseError::InvalidPercentage(
+                                self.src.clone(),
+                                self.first_entry(child)?.span(),
+                            ))
+                        }
+                    };
+                }
+                "visible" => {
+                    preview.visible = self.first_entry_as_bool(child)?;
+                }
+                option => {
+                    return Err(ParseError::UnknownConfigurationOption(
+                        format!("preview.{}", option),
+                        self.src.clone(),
+                        child.name().span(),
+                    ));
+                }
+            }
+        }
+
+        Ok(preview)
+    }
+
+    fn parse_theme(&self, node: &KdlNode, mut theme: Theme) -> Result<Theme, ParseError> {
+        let doc = node
+            .children()
+            .ok_or(ParseError::MissingChildNode(self.src.clone(), node.span()))?;
+
+        for child in doc.nodes() {
+            let color = match child.name().value() {
+                "prompt" => &mut theme.prompt,
+                "highlight" => &mut theme.highlight,
+                "match" => &mut theme.matched,
+                "border" => &mut theme.border,
+                "counter" => &mut theme.counter,
+                "marked" => &mut theme.marked,
+                "preview" => &mut theme.preview,
+                option => {
+                    return Err(ParseError::UnknownConfigurationOption(
+                        format!("theme.{}", option),
+                        self.src.clone(),
+                        child.name().span(),
+                    ));
+                }
+            };
+
+            let entry = self.first_entry(child)?;
+            *color = match entry.value() {
+                KdlValue::String(s) if s == "default" => Some(Color::Reset),
+                KdlValue::String(s) => s.parse().ok(),
+                KdlValue::Integer(index) => u8::try_from(*index).ok().map(Color::Indexed),
+                _ => None,
+            }
+            .ok_or(ParseError::InvalidColor(self.src.clone(), entry.span()))?;
+        }
+
+        Ok(theme)
+    }
+
+    fn parse_keys(&self, node: &KdlNode, keys: &mut Keys) -> Result<(), ParseError> {
+        let doc = node
+            .children()
+            .ok_or(ParseError::MissingChildNode(self.src.clone(), node.span()))?;
+
+        for child in doc.nodes() {
+            let chord =
+                child.name().value().parse::<KeyChord>().map_err(|_| {
+                    ParseError::InvalidKeyChord(self.src.clone(), child.name().span())
+                })?;
+
+            let entry = self.first_entry(child)?;
+            match entry.value().as_string() {
+                Some("none") => keys.unbind(&chord),
+                Some(action) => match action.parse::<Action>() {
+                    Ok(action) => keys.bind(chord, action),
+                    Err(_) => {
+                        return Err(ParseError::UnknownAction(
+                            Action::ALL.map(|a| format!("'{}'", a)).join(", "),
+                            self.src.clone(),
+                            entry.span(),
+                        ))
+                    }
+                },
+                None => {
+                    return Err(ParseError::TypeMismatch(
+                        "string",
+                        type_from_value(entry.value()),
+                        self.src.clone(),
+                        entry.span(),
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn first_entry<'a>(&'a self, node: &'a KdlNode) -> Result<&'a KdlEntry, ParseError> {
         node.entries()
             .iter()
@@ -110,16 +416,40 @@ impl Parser {
             .ok_or(ParseError::MissingValue(self.src.clone(), node.span()))
     }
 
-    // fn first_entry_as_string<'a>(&'a self, node: &'a KdlNode) -> Result<&'a str, ParseError> {
-    //     self.first_entry(node).and_then(|entry| {
-    //         entry.value().as_string().ok_or(ParseError::TypeMismatch(
-    //             "string",
-    //             type_from_value(entry.value()),
-    //             self.src.clone(),
-    //             *entry.span(),
-    //         ))
-    //     })
-    // }
+    fn first_entry_as_string<'a>(&'a self, node: &'a KdlNode) -> Result<&'a str, ParseError> {
+        let entry = node.entry(0).ok_or(ParseError::MissingPositionalEntry(
+            "string",
+            self.src.clone(),
+            node.span(),
+        ))?;
+        entry.value().as_string().ok_or(ParseError::TypeMismatch(
+            "string",
+            type_from_value(entry.value()),
+            self.src.clone(),
+            entry.span(),
+        ))
+    }
+
+    fn optional_entry_as_string(
+        &self,
+        node: &KdlNode,
+        index: usize,
+    ) -> Result<Option<String>, ParseError> {
+        node.entry(index)
+            .map(|entry| {
+                entry
+                    .value()
+                    .as_string()
+                    .map(|s| s.to_string())
+                    .ok_or(ParseError::TypeMismatch(
+                        "string",
+                        type_from_value(entry.value()),
+                        self.src.clone(),
+                        entry.span(),
+                    ))
+            })
+            .transpose()
+    }
 
     fn first_entry_as_i64<'a>(&'a self, node: &'a KdlNode) -> Result<i64, ParseError> {
         self.first_entry(node).and_then(|entry| {
@@ -136,6 +466,16 @@ impl Parser {
         })
     }
 
+    fn entry_as_depth(&self, entry: &KdlEntry) -> Result<usize, ParseError> {
+        let depth = entry.value().as_integer().ok_or(ParseError::TypeMismatch(
+            "integer",
+            type_from_value(entry.value()),
+            self.src.clone(),
+            entry.span(),
+        ))?;
+        usize::try_from(depth).map_err(|_| ParseError::InvalidDepth(self.src.clone(), entry.span()))
+    }
+
     fn first_entry_as_bool<'a>(&'a self, node: &'a KdlNode) -> Result<bool, ParseError> {
         self.first_entry(node).and_then(|entry| {
             entry.value().as_bool().ok_or(ParseError::TypeMismatch(
@@ -180,6 +520,44 @@ impl Parser {
             .try_collect()
     }
 
+    fn try_get_workspaces(&self, doc: &KdlDocument) -> Result<Vec<Workspace>, ParseError> {
+        doc.get("workspace")
+            .and_then(|n| n.children())
+            .map(|doc| doc.nodes())
+            .unwrap_or_default()
+            .iter()
+            .filter(|n| n.name().value() == "-")
+            .map(|node| {
+                let path = shellexpand::tilde(self.first_entry_as_string(node)?).to_string();
+                let depth = node
+                    .entry("depth")
+                    .map(|entry| self.entry_as_depth(entry))
+                    .transpose()?;
+                Ok(Workspace { path, depth })
+            })
+            .try_collect()
+    }
+
+    /// Reject exclude rules with a `/` that are relative. They would be matched against the
+    /// absolute paths of the walk and never match.
+    fn check_exclude_paths(&self, doc: &KdlDocument) -> Result<(), ParseError> {
+        let entries = get_dash_entries(doc, "exclude_path");
+        match entries.into_iter().find(|entry| {
+            entry.value().as_string().is_some_and(|pattern| {
+                let pattern = pattern.strip_prefix('!').unwrap_or(pattern);
+                pattern.contains('/')
+                    && !pattern.starts_with(['/', '~'])
+                    && !pattern.starts_with("**")
+            })
+        }) {
+            Some(entry) => Err(ParseError::InvalidExcludePath(
+                self.src.clone(),
+                entry.span(),
+            )),
+            None => Ok(()),
+        }
+    }
+
     fn try_get_dash_values_as_valid_paths(
         &self,
         doc: &KdlDocument,
@@ -201,3 +579,59 @@ impl Parser {
             .try_collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use miette::SourceSpan;
+
+    use super::{ParseError, Parser, Source};
+    use crate::config::Config;
+
+    fn parse(raw: &str) -> Result<Config, ParseError> {
+        Parser::new(Source::new("config.kdl".to_string(), raw.to_string())).parse()
+    }
+
+    /// Span of the first occurrence of `text` in `raw`
+    fn span(raw: &str, text: &str) -> SourceSpan {
+        (raw.find(text).unwrap(), text.len()).into()
+    }
+
+    #[test]
+    fn parse_exclude_paths() {
+        let config =
+            parse(r#"exclude_path { - "**/vendor/**"; - "~/code/archive"; - "!/srv/keep" }"#);
+        assert!(config.is_ok());
+    }
+
+    #[test]
+    fn reject_relative_exclude_paths() {
+        let raw = r#"exclude_path { - "target"; - "code/archive" }"#;
+        match parse(raw) {
+            Err(ParseError::InvalidExcludePath(_, at)) => {
+                assert_eq!(at, span(raw, r#""code/archive""#))
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn reject_invalid_key_chords() {
+        let raw = r#"keys { ctrl-j "down"; hyper-k "up" }"#;
+        match parse(raw) {
+            Err(ParseError::InvalidKeyChord(_, at)) => assert_eq!(at, span(raw, "hyper-k")),
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn reject_unknown_actions() {
+        let raw = r#"keys { ctrl-j "down"; alt-k "upp" }"#;
+        match parse(raw) {
+            Err(ParseError::UnknownAction(actions, _, at)) => {
+                assert!(actions.contains("'up'"));
+                assert_eq!(at, span(raw, r#""upp""#));
+            }
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
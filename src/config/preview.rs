@@ -0,0 +1,27 @@
+/// Side of the picker the preview is shown on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewPosition {
+    #[default]
+    Right,
+    Top,
+}
+
+/// Placement of the preview area in the picker.
+#[derive(Debug, Clone, Copy)]
+pub struct Preview {
+    pub position: PreviewPosition,
+    /// Percentage of the picker used by the preview
+    pub size: u16,
+    /// Show the preview when the picker opens. It can always be toggled with `ctrl-t`.
+    pub visible: bool,
+}
+
+impl Default for Preview {
+    fn default() -> Self {
+        Self {
+            position: PreviewPosition::default(),
+            size: 50,
+            visible: false,
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::{fmt, path::Path, str::FromStr};
+
+use gix::bstr::ByteSlice;
+
+use crate::util;
+
+/// Value of a workspace substituted into a [`SessionName`] template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder {
+    Basename,
+    /// Name of the directory containing the workspace
+    Parent,
+    /// Name of the git repository, shared by all of its worktrees
+    Repo,
+    Branch,
+    /// Name of the linked git worktree, empty in the main worktree
+    Worktree,
+    /// Owner of the default remote such as the user or organization on GitHub
+    RemoteOwner,
+}
+
+impl Placeholder {
+    pub const ALL: [Placeholder; 6] = [
+        Placeholder::Basename,
+        Placeholder::Parent,
+        Placeholder::Repo,
+        Placeholder::Branch,
+        Placeholder::Worktree,
+        Placeholder::RemoteOwner,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Placeholder::Basename => "basename",
+            Placeholder::Parent => "parent",
+            Placeholder::Repo => "repo",
+            Placeholder::Branch => "branch",
+            Placeholder::Worktree => "worktree",
+            Placeholder::RemoteOwner => "remote_owner",
+        }
+    }
+
+    fn needs_repo(self) -> bool {
+        !matches!(self, Placeholder::Basename | Placeholder::Parent)
+    }
+
+    fn value(self, path: &Path, repo: Option<&gix::Repository>) -> Option<String> {
+        match self {
+            Placeholder::Basename => file_name(path),
+            Placeholder::Parent => path.parent().and_then(file_name),
+            Placeholder::Repo => repo.and_then(repo_name),
+            Placeholder::Branch => repo?
+                .head_name()
+                .ok()
+                .flatten()
+                .map(|name| name.shorten().to_string()),
+            Placeholder::Worktree => repo
+                .filter(|repo| repo.git_dir() != repo.common_dir())
+                .and_then(|repo| file_name(repo.git_dir())),
+            Placeholder::RemoteOwner => repo.and_then(remote_owner),
+        }
+    }
+}
+
+impl FromStr for Placeholder {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Placeholder::ALL
+            .into_iter()
+            .find(|placeholder| placeholder.name() == s)
+            .ok_or(())
+    }
+}
+
+impl fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{{}}}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// Template the names of new sessions are built from, such as `{repo}/{branch}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionName(Vec<Segment>);
+
+impl SessionName {
+    /// Name of the session for the workspace at `path`. Placeholders without a value, such as
+    /// `{branch}` outside of a git repository, are left empty along with the separators before
+    /// them, or after them at the start of the name. The basename is used if nothing is left.
+    /// Characters tmux does not allow in names are replaced with [`util::format_name`].
+    pub fn render(&self, path: &Path) -> String {
+        let repo = self
+            .0
+            .iter()
+            .any(|segment| matches!(segment, Segment::Placeholder(p) if p.needs_repo()))
+            .then(|| gix::open(path).ok())
+            .flatten();
+
+        let mut name = String::new();
+        let mut trim_start = false;
+        for segment in &self.0 {
+            match segment {
+                Segment::Text(text) => {
+                    let text = match trim_start {
+                        true => text.trim_start_matches(is_separator),
+                        false => text,
+                    };
+                    trim_start = trim_start && text.is_empty();
+                    name.push_str(text);
+                }
+                Segment::Placeholder(placeholder) => match placeholder.value(path, repo.as_ref()) {
+                    Some(value) if !value.is_empty() => name.push_str(&value),
+                    _ if name.is_empty() => trim_start = true,
+                    _ => name.truncate(name.trim_end_matches(is_separator).len()),
+                },
+            }
+        }
+
+        match name.is_empty() {
+            true => util::format_name(&file_name(path).unwrap_or_default()),
+            false => util::format_name(&name),
+        }
+    }
+}
+
+impl Default for SessionName {
+    fn default() -> Self {
+        Self(vec![Segment::Placeholder(Placeholder::Basename)])
+    }
+}
+
+impl FromStr for SessionName {
+    type Err = ();
+
+    /// Parse text with placeholders in braces. Unknown placeholders and unbalanced braces are
+    /// rejected.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut segments = vec![];
+        let mut rest = s;
+        while let Some(start) = rest.find(['{', '}']) {
+            let (text, tail) = rest.split_at(start);
+            let end = tail.find('}').filter(|_| tail.starts_with('{')).ok_or(())?;
+            if !text.is_empty() {
+                segments.push(Segment::Text(text.to_string()));
+            }
+            segments.push(Segment::Placeholder(tail[1..end].parse()?));
+            rest = &tail[end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+
+        match segments.is_empty() {
+            true => Err(()),
+            false => Ok(Self(segments)),
+        }
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, '-' | '_' | '/' | '.' | ':') || c.is_whitespace()
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+/// Name of the directory holding the repository. Hidden git directories such as `.git` or the
+/// `.bare` directory of a bare repository with worktrees are named after their parent.
+fn repo_name(repo: &gix::Repository) -> Option<String> {
+    // Linked worktrees point to the common directory with a relative path such as `../..`
+    let common = std::fs::canonicalize(repo.common_dir()).ok()?;
+    let common = common.as_path();
+    let dir = match file_name(common)?.starts_with('.') {
+        true => common.parent()?,
+        false => common,
+    };
+    file_name(dir).map(|name| name.trim_end_matches(".git").to_string())
+}
+
+/// Second to last component of the default remote url, `owner` in
+/// `git@github.com:owner/repo.git` or `https://github.com/owner/repo`.
+fn remote_owner(repo: &gix::Repository) -> Option<String> {
+    let remote = repo
+        .find_default_remote(gix::remote::Direction::Fetch)?
+        .ok()?;
+    let url = remote.url(gix::remote::Direction::Fetch)?;
+    url.path
+        .to_str_lossy()
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .nth_back(1)
+        .map(|owner| owner.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::SessionName;
+
+    fn render(template: &str, path: &str) -> String {
+        template
+            .parse::<SessionName>()
+            .unwrap()
+            .render(Path::new(path))
+    }
+
+    #[test]
+    fn parse_placeholders_and_text() {
+        assert!("{repo}/{branch}".parse::<SessionName>().is_ok());
+        assert!("work-{basename}".parse::<SessionName>().is_ok());
+        assert!("work".parse::<SessionName>().is_ok());
+    }
+
+    #[test]
+    fn reject_invalid_templates() {
+        assert!("".parse::<SessionName>().is_err());
+        assert!("{}".parse::<SessionName>().is_err());
+        assert!("{repo".parse::<SessionName>().is_err());
+        assert!("repo}".parse::<SessionName>().is_err());
+        assert!("{repo}}".parse::<SessionName>().is_err());
+        assert!("{{repo}".parse::<SessionName>().is_err());
+        assert!("{name}".parse::<SessionName>().is_err());
+    }
+
+    #[test]
+    fn render_placeholders() {
+        assert_eq!(render("{parent}/{basename}", "/src/oss/api"), "oss/api");
+        assert_eq!(render("work-{basename}", "/src/oss/api"), "work-api");
+        assert_eq!(render("{basename}.rs", "/src/oss/api"), "api_rs");
+    }
+
+    #[test]
+    fn keep_literal_separators() {
+        assert_eq!(render("-{basename}-", "/src/oss/api"), "-api-");
+        assert_eq!(render("{parent}--{basename}", "/src/oss/api"), "oss--api");
+    }
+
+    #[test]
+    fn drop_separators_of_empty_placeholders() {
+        // Outside of a git repository the repo placeholders have no value
+        assert_eq!(render("{basename}/{branch}", "/src/oss/api"), "api");
+        assert_eq!(render("{branch}-{basename}", "/src/oss/api"), "api");
+        assert_eq!(
+            render("{parent}-{worktree}-{basename}", "/src/oss/api"),
+            "oss-api"
+        );
+        assert_eq!(render("{repo}/{branch}", "/src/oss/api"), "api");
+    }
+}
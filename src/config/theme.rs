@@ -0,0 +1,57 @@
+use ratatui::style::Color;
+
+/// Colors of the picker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub prompt: Color,
+    /// Item under the cursor
+    pub highlight: Color,
+    /// Matched characters of the items
+    pub matched: Color,
+    pub border: Color,
+    /// Match counter and match mode
+    pub counter: Color,
+    /// Marker in front of the selected items
+    pub marked: Color,
+    /// Text of the preview
+    pub preview: Color,
+}
+
+impl Theme {
+    /// Theme without any colors. The item under the cursor and the matched characters are
+    /// still shown with the `>` marker and in bold.
+    pub fn no_color() -> Self {
+        Self {
+            prompt: Color::Reset,
+            highlight: Color::Reset,
+            matched: Color::Reset,
+            border: Color::Reset,
+            counter: Color::Reset,
+            marked: Color::Reset,
+            preview: Color::Reset,
+        }
+    }
+
+    /// The theme, or [`Theme::no_color`] if the `NO_COLOR` environment variable is set to a
+    /// non-empty value (https://no-color.org).
+    pub fn or_no_color(self) -> Self {
+        match std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            true => Self::no_color(),
+            false => self,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            prompt: Color::LightBlue,
+            highlight: Color::LightBlue,
+            matched: Color::LightGreen,
+            border: Color::DarkGray,
+            counter: Color::LightYellow,
+            marked: Color::LightMagenta,
+            preview: Color::Reset,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    io::Write,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use indexmap::IndexMap;
+use miette::{IntoDiagnostic, Result};
+
+use crate::util;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// Once the counts of all entries add up to this value they are scaled down so old entries
+/// eventually drop out.
+const MAX_TOTAL: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Entry {
+    pub count: u64,
+    /// Seconds since the unix epoch of the last attach
+    pub last: u64,
+}
+
+impl Entry {
+    pub fn score(&self, now: u64) -> f64 {
+        let weight = match now.saturating_sub(self.last) {
+            0..HOUR => 4.0,
+            HOUR..DAY => 2.0,
+            DAY..WEEK => 0.5,
+            _ => 0.25,
+        };
+        self.count as f64 * weight
+    }
+}
+
+/// How often and how recently each workspace was attached to.
+#[derive(Debug, Default)]
+pub struct Frecency(pub IndexMap<String, Entry>);
+
+impl Frecency {
+    pub fn path() -> PathBuf {
+        util::get_local(&["frecency"])
+    }
+
+    pub fn new() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Frecency::default());
+        }
+
+        let content = util::read_content(path)?;
+        Ok(Frecency(
+            content
+                .lines()
+                .filter_map(|line| {
+                    let mut parts = line.splitn(3, '\t');
+                    let count = parts.next()?.parse().ok()?;
+                    let last = parts.next()?.parse().ok()?;
+                    let path = parts.next()?;
+                    Some((path.to_owned(), Entry { count, last }))
+                })
+                .collect(),
+        ))
+    }
+
+    /// Record an attach to `path`
+    pub fn add(&mut self, path: String) {
+        let now = now();
+        let entry = self.0.entry(path).or_insert(Entry {
+            count: 0,
+            last: now,
+        });
+        entry.count += 1;
+        entry.last = now;
+
+        if self.0.values().map(|e| e.count).sum::<u64>() > MAX_TOTAL {
+            self.0.retain(|_, e| {
+                e.count = e.count * 9 / 10;
+                e.count > 0
+            });
+        }
+    }
+
+    pub fn score(&self, path: &str) -> f64 {
+        let now = now();
+        self.0.get(path).map_or(0.0, |e| e.score(now))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn write(&self) -> Result<()> {
+        util::write(Frecency::path(), |f| {
+            for (path, e) in &self.0 {
+                f.write_fmt(format_args!("{}\t{}\t{}\n", e.count, e.last, path))
+                    .into_diagnostic()?;
+            }
+            Ok(())
+        })
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,57 @@
+use std::{io::Write, path::PathBuf};
+
+use miette::{IntoDiagnostic, Result};
+
+use crate::util;
+
+/// Number of queries kept in the history
+const MAX_ENTRIES: usize = 500;
+
+/// Queries accepted in the picker, oldest first.
+#[derive(Debug, Default, Clone)]
+pub struct History(pub Vec<String>);
+
+impl History {
+    pub fn path() -> PathBuf {
+        util::get_local(&["history"])
+    }
+
+    pub fn new() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(History::default());
+        }
+
+        let content = util::read_content(path)?;
+        Ok(History(
+            content
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_owned())
+                .collect(),
+        ))
+    }
+
+    /// Record `query` as the most recent entry
+    pub fn add(&mut self, query: &str) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+
+        self.0.retain(|entry| entry != query);
+        self.0.push(query.to_owned());
+        if self.0.len() > MAX_ENTRIES {
+            self.0.drain(..self.0.len() - MAX_ENTRIES);
+        }
+    }
+
+    pub fn write(&self) -> Result<()> {
+        util::write(History::path(), |f| {
+            for query in &self.0 {
+                f.write_fmt(format_args!("{}\n", query)).into_diagnostic()?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use miette::{IntoDiagnostic, Result};
+
+use crate::{config::Config, util, walker::Walker};
+
+/// Workspaces found by the last walk of the configured search paths. Used to show the list of
+/// workspaces without waiting for a new walk to complete.
+#[derive(Debug, Default)]
+pub struct Index(pub Vec<String>);
+
+impl Index {
+    pub fn path() -> PathBuf {
+        util::get_local(&["workspaces"])
+    }
+
+    pub fn new() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Index(vec![]));
+        }
+
+        let content = util::read_content(path)?;
+        Ok(Index(content.lines().map(|e| e.to_owned()).collect()))
+    }
+
+    /// Walk the search paths and store the result.
+    pub fn walk(config: &Config) -> Result<Self> {
+        let index = Index(config.paths_from_walk());
+        index.write()?;
+        Ok(index)
+    }
+
+    /// Walk the search paths and update the stored index. Paths not in the current index are
+    /// passed to `push` as soon as they are found. If any path in the current index no longer
+    /// exists the complete new list is returned.
+    pub fn refresh<F>(&self, config: &Config, mut push: F) -> Result<Option<Vec<String>>>
+    where
+        F: FnMut(&str),
+    {
+        let known: HashSet<&str> = self.0.iter().map(|e| e.as_str()).collect();
+        let mut fresh = Index::default();
+        config.walk(|path| {
+            if !known.contains(path.as_str()) {
+                push(&path);
+            }
+            fresh.0.push(path);
+        });
+        fresh.write()?;
+
+        let found: HashSet<&str> = fresh.0.iter().map(|e| e.as_str()).collect();
+        if self.0.iter().any(|e| !found.contains(e.as_str())) {
+            Ok(Some(fresh.0))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Walk the search paths and store the result in a `tux list --all` process that keeps
+    /// running after this process exits.
+    pub fn refresh_detached() -> Result<()> {
+        Command::new(std::env::current_exe().into_diagnostic()?)
+            .args(["list", "--all"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .into_diagnostic()?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn write(&self) -> Result<()> {
+        util::write(Index::path(), |f| {
+            for e in &self.0 {
+                f.write_fmt(format_args!("{}\n", e)).into_diagnostic()?;
+            }
+            Ok(())
+        })
+    }
+}
@@ -1,5 +1,8 @@
 pub mod cmd;
 pub mod config;
+pub mod frecency;
+pub mod history;
+pub mod index;
 pub mod jumplist;
 pub mod mux;
 pub mod ui;
@@ -61,7 +61,7 @@ fn main() -> Result<()> {
         Some(first) => {
             if first == "." {
                 let config = Config::load()?;
-                return cmd::Attach::default().use_cwd(&config);
+                return cmd::Attach::default().use_cwd(config);
             }
 
             let starts_with_long = first.starts_with("--");
@@ -0,0 +1,272 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::{miette, Result};
+
+use crate::config::{Layout, Split};
+
+use super::{Multiplexer, SessionInfo};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FakeSession {
+    pub path: PathBuf,
+    pub windows: Vec<String>,
+    pub env: IndexMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    sessions: IndexMap<String, FakeSession>,
+    current: Option<String>,
+    attached: Vec<String>,
+    commands: Vec<(String, String)>,
+    pane_count: usize,
+}
+
+/// In-memory multiplexer that records every operation. Used to exercise commands without a
+/// running multiplexer server.
+#[derive(Debug, Default)]
+pub struct FakeMux {
+    state: Mutex<State>,
+}
+
+impl FakeMux {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an existing session with a single window
+    pub fn with_session<P: AsRef<Path>>(self, name: &str, path: P) -> Self {
+        self.state().sessions.insert(
+            name.to_string(),
+            FakeSession {
+                path: path.as_ref().to_owned(),
+                windows: vec!["0".to_string()],
+                env: IndexMap::new(),
+            },
+        );
+        self
+    }
+
+    /// Set the session the current process is running in
+    pub fn with_current(self, name: &str) -> Self {
+        self.state().current = Some(name.to_string());
+        self
+    }
+
+    pub fn session(&self, name: &str) -> Option<FakeSession> {
+        self.state().sessions.get(name).cloned()
+    }
+
+    /// Names of the sessions attached to, in order
+    pub fn attached(&self) -> Vec<String> {
+        self.state().attached.clone()
+    }
+
+    /// Pairs of `(target, command)` sent to panes, in order
+    pub fn commands(&self) -> Vec<(String, String)> {
+        self.state().commands.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("fake mux lock is not poisoned")
+    }
+}
+
+impl Multiplexer for FakeMux {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn list_sessions(&self) -> Vec<String> {
+        self.state().sessions.keys().cloned().collect()
+    }
+
+    /// The current session is reported as attached
+    fn list_session_details(&self) -> Vec<SessionInfo> {
+        let state = self.state();
+        state
+            .sessions
+            .iter()
+            .map(|(name, session)| SessionInfo {
+                name: name.clone(),
+                path: Some(session.path.clone()),
+                windows: session.windows.len(),
+                attached: state.current.as_ref() == Some(name),
+            })
+            .collect()
+    }
+
+    fn session_exists(&self, name: &str) -> bool {
+        let state = self.state();
+        match name.split_once(':') {
+            Some((session, window)) => state
+                .sessions
+                .get(session)
+                .is_some_and(|s| s.windows.iter().any(|w| w == window)),
+            None => state.sessions.contains_key(name),
+        }
+    }
+
+    fn create_session(
+        &self,
+        name: &str,
+        path: &Path,
+        window_name: Option<&str>,
+        layout: Option<&Layout>,
+        env: &IndexMap<String, String>,
+    ) -> Result<()> {
+        let mut state = self.state();
+        if state.sessions.contains_key(name) {
+            return Err(miette!("duplicate session: {}", name));
+        }
+
+        let mut windows = vec![];
+        let mut commands = vec![];
+        for (index, window) in layout
+            .map(|l| l.windows.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+        {
+            let window_name = window.name.clone().unwrap_or(index.to_string());
+            let target = format!("{name}:{window_name}");
+            commands.extend(
+                window
+                    .panes
+                    .iter()
+                    .filter_map(|p| p.command.clone())
+                    .map(|c| (target.clone(), c)),
+            );
+            windows.push(window_name);
+        }
+        if windows.is_empty() {
+            windows.push(window_name.unwrap_or("0").to_string());
+        }
+
+        state.commands.extend(commands);
+        state.sessions.insert(
+            name.to_string(),
+            FakeSession {
+                path: path.to_owned(),
+                windows,
+                env: env.clone(),
+            },
+        );
+        Ok(())
+    }
+
+    fn attach_session(&self, name: &str) -> Result<()> {
+        let mut state = self.state();
+        if !state.sessions.contains_key(name) {
+            return Err(miette!("can't find session: {}", name));
+        }
+        state.attached.push(name.to_string());
+        state.current = Some(name.to_string());
+        Ok(())
+    }
+
+    fn kill_session(&self, name: &str) -> Result<()> {
+        let mut state = self.state();
+        state
+            .sessions
+            .shift_remove(name)
+            .ok_or(miette!("can't find session: {}", name))?;
+        if state.current.as_deref() == Some(name) {
+            state.current = None;
+        }
+        Ok(())
+    }
+
+    fn rename_session(&self, name: &str, new_name: &str) -> Result<()> {
+        let mut state = self.state();
+        if state.sessions.contains_key(new_name) {
+            return Err(miette!("duplicate session: {}", new_name));
+        }
+        let index = state
+            .sessions
+            .get_index_of(name)
+            .ok_or(miette!("can't find session: {}", name))?;
+        let (_, session) = state
+            .sessions
+            .shift_remove_index(index)
+            .expect("index was looked up");
+        state
+            .sessions
+            .shift_insert(index, new_name.to_string(), session);
+        if state.current.as_deref() == Some(name) {
+            state.current = Some(new_name.to_string());
+        }
+        Ok(())
+    }
+
+    fn session_name(&self) -> Option<String> {
+        self.state().current.clone()
+    }
+
+    fn list_windows(&self, session: &str) -> Vec<String> {
+        self.state()
+            .sessions
+            .get(session)
+            .map(|s| s.windows.clone())
+            .unwrap_or_default()
+    }
+
+    fn create_window(&self, name: &str, _path: Option<&Path>) -> Result<()> {
+        let mut state = self.state();
+        let current = state.current.clone().ok_or(miette!("no current session"))?;
+        state
+            .sessions
+            .get_mut(&current)
+            .ok_or(miette!("can't find session: {}", current))?
+            .windows
+            .push(name.to_string());
+        Ok(())
+    }
+
+    fn split_window(&self, target: &str, _split: Split, _path: &Path) -> Result<String> {
+        let mut state = self.state();
+        state.pane_count += 1;
+        Ok(format!("{}.{}", target, state.pane_count))
+    }
+
+    fn send_command(&self, target: &str, command: &str) -> Result<()> {
+        self.state()
+            .commands
+            .push((target.to_string(), command.to_string()));
+        Ok(())
+    }
+
+    fn capture_pane(&self, target: &str) -> Result<String> {
+        Ok(self
+            .state()
+            .commands
+            .iter()
+            .filter(|(t, _)| t == target)
+            .map(|(_, c)| c.as_str())
+            .join("\n"))
+    }
+
+    fn set_environment(&self, session: &str, key: &str, value: &str) -> Result<()> {
+        self.state()
+            .sessions
+            .get_mut(session)
+            .ok_or(miette!("can't find session: {}", session))?
+            .env
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn set_session_path(&self, session: &str, path: &Path) -> Result<()> {
+        self.state()
+            .sessions
+            .get_mut(session)
+            .ok_or(miette!("can't find session: {}", session))?
+            .path = path.to_owned();
+        Ok(())
+    }
+}
@@ -1,59 +1,402 @@
-use std::path::Path;
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock, RwLock},
+};
 
+use indexmap::IndexMap;
 use miette::Result;
 
+use crate::{
+    config::{Layout, Split},
+    util,
+};
+
+mod fake;
 mod tmux;
+mod zellij;
+
+pub use fake::{FakeMux, FakeSession};
 
-#[derive(Debug, Default)]
-pub enum Mux {
-    #[default]
-    Tmux,
+/// A running session as listed by [`Multiplexer::list_session_details`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    pub name: String,
+    /// Path the session was opened from by tux, if the backend records it
+    pub path: Option<PathBuf>,
+    pub windows: usize,
+    /// A client is attached to the session
+    pub attached: bool,
 }
 
-impl Mux {
-    pub fn list_sessions(&self) -> Vec<String> {
+/// Interface to a terminal multiplexer backend.
+///
+/// Targets follow the tmux convention where a session is addressed by its name and a window
+/// inside of a session by `session:window`. Backends are free to interpret pane targets in the
+/// format returned by [`Multiplexer::split_window`].
+pub trait Multiplexer: Debug + Send + Sync {
+    /// Name the backend is registered under
+    fn name(&self) -> &'static str;
+
+    fn list_sessions(&self) -> Vec<String>;
+
+    /// Running sessions with their path, window count and whether they are attached. Backends
+    /// that can not report the details only list the names and windows of the sessions.
+    fn list_session_details(&self) -> Vec<SessionInfo> {
+        self.list_sessions()
+            .into_iter()
+            .map(|name| SessionInfo {
+                windows: self.list_windows(&name).len(),
+                name,
+                path: None,
+                attached: false,
+            })
+            .collect()
+    }
+
+    fn session_exists(&self, name: &str) -> bool;
+
+    /// Running session that was opened from `path`
+    fn find_session(&self, path: &Path) -> Option<String> {
+        self.list_session_details()
+            .into_iter()
+            .find(|session| session.path.as_deref() == Some(path))
+            .map(|session| session.name)
+    }
+
+    /// Name of the session for the workspace at `path`, based on `name`. The session opened
+    /// from `path` is reused if it is running. Otherwise `name` is prefixed with the parent
+    /// directories of `path` (`api`, `oss/api`) until it does not collide with a session opened
+    /// from another path. Sessions without a known path are matched by name.
+    fn resolve_session_name(&self, name: &str, path: &Path) -> String {
+        let sessions = self.list_session_details();
+        if let Some(session) = sessions
+            .iter()
+            .find(|session| session.path.as_deref() == Some(path))
+        {
+            return session.name.clone();
+        }
+
+        let taken = |candidate: &str| {
+            sessions
+                .iter()
+                .any(|session| session.name == candidate && session.path.is_some())
+        };
+        let mut candidate = name.to_string();
+        let parents = path.parent().into_iter().flat_map(|parent| {
+            parent
+                .components()
+                .rev()
+                .filter_map(|c| c.as_os_str().to_str().filter(|c| *c != "/"))
+        });
+        for parent in parents {
+            if !taken(&candidate) {
+                return candidate;
+            }
+            candidate = format!("{}/{}", util::format_name(parent), candidate);
+        }
+
+        (1..)
+            .map(|n| match n {
+                1 => candidate.clone(),
+                n => format!("{}-{}", candidate, n),
+            })
+            .find(|candidate| !taken(candidate))
+            .expect("there is always a free name")
+    }
+
+    fn create_session(
+        &self,
+        name: &str,
+        path: &Path,
+        window_name: Option<&str>,
+        layout: Option<&Layout>,
+        env: &IndexMap<String, String>,
+    ) -> Result<()>;
+
+    fn attach_session(&self, name: &str) -> Result<()>;
+
+    fn kill_session(&self, name: &str) -> Result<()>;
+
+    fn rename_session(&self, name: &str, new_name: &str) -> Result<()>;
+
+    /// Name of the session the current process is running in
+    fn session_name(&self) -> Option<String>;
+
+    fn list_windows(&self, session: &str) -> Vec<String>;
+
+    /// Create a window in the current session, started in `path` if given
+    fn create_window(&self, name: &str, path: Option<&Path>) -> Result<()>;
+
+    /// Split the pane `target` and return the target of the new pane
+    fn split_window(&self, target: &str, split: Split, path: &Path) -> Result<String>;
+
+    /// Type `command` into `target` followed by enter
+    fn send_command(&self, target: &str, command: &str) -> Result<()>;
+
+    /// Snapshot of the visible contents of `target`
+    fn capture_pane(&self, target: &str) -> Result<String>;
+
+    fn set_environment(&self, session: &str, key: &str, value: &str) -> Result<()>;
+
+    /// Record the path the session was opened from so it can be found by
+    /// [`Multiplexer::find_session`]
+    fn set_session_path(&self, session: &str, path: &Path) -> Result<()>;
+
+    fn create_or_attach(&self, name: &str, path: &Path) -> Result<()> {
+        let name = self.resolve_session_name(name, path);
+        if !self.session_exists(&name) {
+            self.create_session(&name, path, None, None, &IndexMap::new())?;
+            self.set_session_path(&name, path)?;
+        }
+        self.attach_session(&name)
+    }
+}
+
+pub type Constructor = fn() -> Box<dyn Multiplexer>;
+
+/// Named constructors of the available multiplexer backends. The `tmux` and `zellij` backends
+/// are always registered.
+#[derive(Debug)]
+pub struct Registry {
+    backends: IndexMap<&'static str, Constructor>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self {
+            backends: IndexMap::new(),
+        };
+        registry.register("tmux", || Box::new(Tmux));
+        registry.register("zellij", || Box::new(Zellij));
+        registry
+    }
+}
+
+impl Registry {
+    pub fn register(&mut self, name: &'static str, constructor: Constructor) {
+        self.backends.insert(name, constructor);
+    }
+
+    pub fn create(&self, name: &str) -> Option<Box<dyn Multiplexer>> {
+        self.backends.get(name).map(|constructor| constructor())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.backends.keys().copied()
+    }
+}
+
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Register a multiplexer backend that can be selected with the `multiplexer` config option.
+pub fn register(name: &'static str, constructor: Constructor) {
+    registry()
+        .write()
+        .expect("registry lock is not poisoned")
+        .register(name, constructor);
+}
+
+/// Create the backend registered under `name`.
+pub fn create(name: &str) -> Option<Arc<dyn Multiplexer>> {
+    registry()
+        .read()
+        .expect("registry lock is not poisoned")
+        .create(name)
+        .map(Arc::from)
+}
+
+/// Backend used when none is configured. Zellij is selected when running inside of zellij.
+pub fn detect() -> Arc<dyn Multiplexer> {
+    if zellij::in_zellij() {
+        Arc::new(Zellij)
+    } else {
+        Arc::new(Tmux)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Tmux;
+
+impl Multiplexer for Tmux {
+    fn name(&self) -> &'static str {
+        "tmux"
+    }
+
+    fn list_sessions(&self) -> Vec<String> {
         tmux::list_sessions()
     }
 
-    pub fn session_exists(&self, name: &str) -> bool {
+    fn list_session_details(&self) -> Vec<SessionInfo> {
+        tmux::list_session_details()
+    }
+
+    fn session_exists(&self, name: &str) -> bool {
         tmux::session_exists(name)
     }
 
-    pub fn create_session<P: AsRef<Path>>(
+    fn create_session(
         &self,
         name: &str,
-        path: P,
+        path: &Path,
         window_name: Option<&str>,
+        layout: Option<&Layout>,
+        env: &IndexMap<String, String>,
     ) -> Result<()> {
-        tmux::create_session(name, path.as_ref(), window_name)
+        tmux::create_session(name, path, window_name, layout, env)
     }
 
-    pub fn attach_session(&self, name: &str) -> Result<()> {
+    fn attach_session(&self, name: &str) -> Result<()> {
         tmux::attach_session(name)
     }
 
-    pub fn kill_session(&self, name: &str) -> Result<()> {
+    fn kill_session(&self, name: &str) -> Result<()> {
         tmux::kill_session(name)
     }
 
-    pub fn create_window(&self, name: &str) -> Result<()> {
-        tmux::create_window(name)
+    fn rename_session(&self, name: &str, new_name: &str) -> Result<()> {
+        tmux::rename_session(name, new_name)
     }
 
-    pub fn send_command(&self, name: &str, command: &str) -> Result<()> {
-        tmux::send_command(name, command)
+    fn session_name(&self) -> Option<String> {
+        tmux::session_name()
     }
 
-    pub fn session_name(&self) -> Option<String> {
-        tmux::session_name()
+    fn list_windows(&self, session: &str) -> Vec<String> {
+        tmux::list_windows(session)
     }
 
-    pub fn create_or_attach<P: AsRef<Path>>(&self, name: &str, path: P) -> Result<()> {
-        if self.session_exists(name) {
-            self.attach_session(name)
-        } else {
-            self.create_session(name, path.as_ref(), None)?;
-            self.attach_session(name)
-        }
+    fn create_window(&self, name: &str, path: Option<&Path>) -> Result<()> {
+        tmux::create_window(name, path)
+    }
+
+    fn split_window(&self, target: &str, split: Split, path: &Path) -> Result<String> {
+        tmux::split_window(target, split, path)
+    }
+
+    fn send_command(&self, target: &str, command: &str) -> Result<()> {
+        tmux::send_command(target, command)
+    }
+
+    fn capture_pane(&self, target: &str) -> Result<String> {
+        tmux::capture_pane(target)
+    }
+
+    fn set_environment(&self, session: &str, key: &str, value: &str) -> Result<()> {
+        tmux::set_environment(session, key, value)
+    }
+
+    fn set_session_path(&self, session: &str, path: &Path) -> Result<()> {
+        tmux::set_session_path(session, path)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Zellij;
+
+impl Multiplexer for Zellij {
+    fn name(&self) -> &'static str {
+        "zellij"
+    }
+
+    fn list_sessions(&self) -> Vec<String> {
+        zellij::list_sessions()
+    }
+
+    fn session_exists(&self, name: &str) -> bool {
+        zellij::session_exists(name)
+    }
+
+    fn create_session(
+        &self,
+        name: &str,
+        path: &Path,
+        window_name: Option<&str>,
+        layout: Option<&Layout>,
+        env: &IndexMap<String, String>,
+    ) -> Result<()> {
+        zellij::create_session(name, path, window_name, layout, env)
+    }
+
+    fn attach_session(&self, name: &str) -> Result<()> {
+        zellij::attach_session(name)
+    }
+
+    fn kill_session(&self, name: &str) -> Result<()> {
+        zellij::kill_session(name)
+    }
+
+    fn rename_session(&self, name: &str, new_name: &str) -> Result<()> {
+        zellij::rename_session(name, new_name)
+    }
+
+    fn session_name(&self) -> Option<String> {
+        zellij::session_name()
+    }
+
+    fn list_windows(&self, session: &str) -> Vec<String> {
+        zellij::list_windows(session)
+    }
+
+    fn create_window(&self, name: &str, path: Option<&Path>) -> Result<()> {
+        zellij::create_window(name, path)
+    }
+
+    fn split_window(&self, target: &str, split: Split, path: &Path) -> Result<String> {
+        zellij::split_window(target, split, path)
+    }
+
+    fn send_command(&self, target: &str, command: &str) -> Result<()> {
+        zellij::send_command(target, command)
+    }
+
+    fn capture_pane(&self, target: &str) -> Result<String> {
+        zellij::capture_pane(target)
+    }
+
+    fn set_environment(&self, session: &str, key: &str, value: &str) -> Result<()> {
+        zellij::set_environment(session, key, value)
+    }
+
+    fn set_session_path(&self, session: &str, path: &Path) -> Result<()> {
+        zellij::set_session_path(session, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{FakeMux, Multiplexer};
+
+    #[test]
+    fn prefix_colliding_name_with_parent() {
+        let mux = FakeMux::new().with_session("api", "/src/work/api");
+
+        let name = mux.resolve_session_name("api", Path::new("/src/oss/api"));
+        assert_eq!(name, "oss/api");
+    }
+
+    #[test]
+    fn reuse_session_of_path() {
+        let mux = FakeMux::new()
+            .with_session("api", "/src/work/api")
+            .with_session("backend", "/src/oss/api");
+
+        let name = mux.resolve_session_name("api", Path::new("/src/oss/api"));
+        assert_eq!(name, "backend");
+    }
+
+    #[test]
+    fn suffix_name_without_free_parent() {
+        let mux = FakeMux::new()
+            .with_session("api", "/src/api")
+            .with_session("api-2", "/srv/api");
+
+        let name = mux.resolve_session_name("api", Path::new("/api"));
+        assert_eq!(name, "api-3");
     }
 }
@@ -1,12 +1,31 @@
-use std::{borrow::Cow, path::Path};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
 
+use indexmap::IndexMap;
 use itertools::Itertools;
-use miette::{IntoDiagnostic, Result};
+use miette::{miette, IntoDiagnostic, Result};
 use tmux_interface::{
-    AttachSession, DisplayMessage, HasSession, KillSession, ListSessions, NewSession, NewWindow,
-    SendKeys, SwitchClient, Tmux,
+    AttachSession, CapturePane, DisplayMessage, HasSession, KillSession, ListSessions, ListWindows,
+    NewSession, NewWindow, RenameSession, SendKeys, SetEnvironment, SetOption, SplitWindow,
+    SwitchClient, Tmux, TmuxCommand,
 };
 
+use crate::config::{Layout, Split, Window};
+
+use super::SessionInfo;
+
+const PANE_ID_FORMAT: &str = "#{pane_id}";
+/// Session user option holding the path a session was opened from
+const PATH_OPTION: &str = "@tux_path";
+
+/// Target of the session named `name`. Without the `=` prefix tmux also matches a session whose
+/// name starts with `name`, such as `api-server` for `api`.
+fn exact(name: &str) -> String {
+    format!("={}", name)
+}
+
 pub fn list_sessions() -> Vec<String> {
     let output = match Tmux::with_command(ListSessions::new().format("#S")).output() {
         Ok(o) => o,
@@ -18,66 +37,248 @@ pub fn list_sessions() -> Vec<String> {
         .unwrap_or_default()
 }
 
+pub fn list_session_details() -> Vec<SessionInfo> {
+    // Sessions created outside of tux have no path. The directory they were started in is not
+    // necessarily the workspace they belong to.
+    let format = format!(
+        "#{{session_name}}\t#{{{}}}\t#{{session_windows}}\t#{{session_attached}}",
+        PATH_OPTION
+    );
+    let output = match Tmux::with_command(ListSessions::new().format(format)).output() {
+        Ok(o) => o,
+        Err(_) => return Vec::new(),
+    };
+
+    String::from_utf8(output.stdout())
+        .map(|s| {
+            s.lines()
+                .filter_map(|line| {
+                    let mut fields = line.split('\t');
+                    let name = fields.next()?.to_string();
+                    let path = fields.next().filter(|p| !p.is_empty()).map(PathBuf::from);
+                    let windows = fields.next()?.parse().unwrap_or_default();
+                    let attached = fields.next().is_some_and(|a| a != "0");
+                    Some(SessionInfo {
+                        name,
+                        path,
+                        windows,
+                        attached,
+                    })
+                })
+                .collect_vec()
+        })
+        .unwrap_or_default()
+}
+
 pub fn session_exists(name: &str) -> bool {
-    Tmux::with_command(HasSession::new().target_session(name))
+    Tmux::with_command(HasSession::new().target_session(exact(name)))
         .output()
         .map(|out| out.success())
         .unwrap_or(false)
 }
 
-pub fn create_session(name: &str, path: &Path, window_name: Option<&str>) -> Result<()> {
+pub fn create_session(
+    name: &str,
+    path: &Path,
+    window_name: Option<&str>,
+    layout: Option<&Layout>,
+    env: &IndexMap<String, String>,
+) -> Result<()> {
+    let windows = layout.map(|l| l.windows.as_slice()).unwrap_or_default();
+    let first = windows.first();
+
     let mut command = NewSession::new()
         .detached()
         .session_name(name)
-        .start_directory(path.to_string_lossy());
-    command.window_name = window_name.map(Cow::Borrowed);
-    Tmux::with_command(command).output().into_diagnostic()?;
+        .start_directory(path.to_string_lossy())
+        .print()
+        .format(PANE_ID_FORMAT);
+    command.window_name = first
+        .and_then(|w| w.name.as_deref())
+        .or(window_name)
+        .map(Cow::Borrowed);
+    if !env.is_empty() {
+        command.environment = Some(
+            env.iter()
+                .map(|(k, v)| (Cow::Borrowed(k.as_str()), Cow::Borrowed(v.as_str())))
+                .collect(),
+        );
+    }
+    let pane = output_line(command)?;
+
+    if let Some(window) = first {
+        apply_window(window, &pane, path)?;
+    }
+
+    let session_target = format!("{name}:");
+    for window in windows.iter().skip(1) {
+        let mut command = NewWindow::new()
+            .detached()
+            .target_window(session_target.as_str())
+            .start_directory(path.to_string_lossy())
+            .print()
+            .format(PANE_ID_FORMAT);
+        command.window_name = window.name.as_deref().map(Cow::Borrowed);
+        let pane = output_line(command)?;
+        apply_window(window, &pane, path)?;
+    }
+
+    Ok(())
+}
+
+/// Split the window that owns `pane` into the panes described by the layout window and send each
+/// pane its startup command.
+fn apply_window(window: &Window, pane: &str, path: &Path) -> Result<()> {
+    let mut target = pane.to_string();
+    for (index, layout_pane) in window.panes.iter().enumerate() {
+        if index > 0 {
+            target = split_window(&target, layout_pane.split, path)?;
+        }
+
+        if let Some(cmd) = layout_pane.command.as_deref() {
+            send_command(&target, cmd)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn split_window(target: &str, split: Split, path: &Path) -> Result<String> {
+    let command = SplitWindow::new()
+        .target_pane(target)
+        .start_directory(path.to_string_lossy())
+        .print()
+        .format(PANE_ID_FORMAT);
+    let command = match split {
+        Split::Horizontal => command.horizontal(),
+        Split::Vertical => command.vertical(),
+    };
+    output_line(command)
+}
+
+fn output_line<'a, T: Into<TmuxCommand<'a>>>(command: T) -> Result<String> {
+    let output = Tmux::with_command(command).output().into_diagnostic()?;
+    if !output.success() {
+        return Err(miette!(
+            "tmux command failed: {}",
+            String::from_utf8_lossy(&output.stderr()).trim()
+        ));
+    }
+    String::from_utf8(output.stdout())
+        .map(|s| s.trim().to_string())
+        .into_diagnostic()
+}
+
+/// Run `command` and fail with its error output prefixed by `context` if tmux rejects it
+fn run<'a, T: Into<TmuxCommand<'a>>>(command: T, context: &str) -> Result<()> {
+    let output = Tmux::with_command(command).output().into_diagnostic()?;
+    if !output.success() {
+        return Err(miette!(
+            "{}: {}",
+            context,
+            String::from_utf8_lossy(&output.stderr()).trim()
+        ));
+    }
     Ok(())
 }
 
 pub fn attach_session(name: &str) -> Result<()> {
+    let context = format!("failed to attach to session '{}'", name);
     if in_tmux() {
-        Tmux::with_command(SwitchClient::new().target_session(name))
-            .output()
-            .into_diagnostic()?;
+        run(SwitchClient::new().target_session(exact(name)), &context)
     } else {
-        Tmux::with_command(AttachSession::new().target_session(name))
-            .output()
-            .into_diagnostic()?;
+        run(AttachSession::new().target_session(exact(name)), &context)
     }
-    Ok(())
 }
 
 pub fn kill_session(name: &str) -> Result<()> {
-    Tmux::with_command(KillSession::new().target_session(name))
-        .output()
-        .into_diagnostic()?;
-    Ok(())
+    run(
+        KillSession::new().target_session(exact(name)),
+        &format!("failed to kill session '{}'", name),
+    )
 }
 
-pub fn create_window(name: &str) -> Result<()> {
-    Tmux::with_command(NewWindow::new().window_name(name))
-        .output()
-        .into_diagnostic()?;
-    Ok(())
+pub fn rename_session(name: &str, new_name: &str) -> Result<()> {
+    run(
+        RenameSession::new()
+            .target_session(exact(name))
+            .new_name(new_name),
+        &format!("failed to rename session '{}'", name),
+    )
+}
+
+pub fn list_windows(session: &str) -> Vec<String> {
+    let output = match Tmux::with_command(
+        ListWindows::new()
+            .target_session(exact(session))
+            .format("#W"),
+    )
+    .output()
+    {
+        Ok(o) => o,
+        Err(_) => return Vec::new(),
+    };
+
+    String::from_utf8(output.stdout())
+        .map(|s| s.lines().map(|s| s.to_string()).collect_vec())
+        .unwrap_or_default()
+}
+
+pub fn create_window(name: &str, path: Option<&Path>) -> Result<()> {
+    let mut command = NewWindow::new().window_name(name);
+    if let Some(path) = path {
+        command = command.start_directory(path.to_string_lossy());
+    }
+    run(command, &format!("failed to create window '{}'", name))
 }
 
 pub fn send_command(name: &str, command: &str) -> Result<()> {
-    Tmux::with_command(SendKeys::new().target_pane(name).key(command))
-        .output()
-        .into_diagnostic()?;
-    Tmux::with_command(SendKeys::new().target_pane(name).key("C-m"))
+    let context = format!("failed to send command to '{}'", name);
+    run(SendKeys::new().target_pane(name).key(command), &context)?;
+    run(SendKeys::new().target_pane(name).key("C-m"), &context)
+}
+
+pub fn capture_pane(target: &str) -> Result<String> {
+    let output = Tmux::with_command(CapturePane::new().stdout().target_pane(target))
         .output()
         .into_diagnostic()?;
-    Ok(())
+    String::from_utf8(output.stdout()).into_diagnostic()
+}
+
+pub fn set_environment(session: &str, key: &str, value: &str) -> Result<()> {
+    run(
+        SetEnvironment::new()
+            .target_session(exact(session))
+            .name(key)
+            .value(value),
+        &format!("failed to set '{}' in session '{}'", key, session),
+    )
 }
 
+pub fn set_session_path(session: &str, path: &Path) -> Result<()> {
+    run(
+        // Session options are set through the active pane of the session
+        SetOption::new()
+            .target_pane(format!("{}:", exact(session)))
+            .option(PATH_OPTION)
+            .value(path.to_string_lossy()),
+        &format!("failed to set the path of session '{}'", session),
+    )
+}
+
+/// Name of the session of the current client. Outside of tmux the server would answer with the
+/// most recently used session instead.
 pub fn session_name() -> Option<String> {
+    if !in_tmux() {
+        return None;
+    }
+
     Tmux::with_command(DisplayMessage::new().print().message("#S"))
         .output()
         .into_diagnostic()
         .and_then(|out| String::from_utf8(out.stdout()).into_diagnostic())
         .ok()
+        .map(|name| name.trim().to_string())
 }
 
 fn in_tmux() -> bool {
@@ -0,0 +1,246 @@
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use indexmap::IndexMap;
+use itertools::Itertools;
+use miette::{miette, IntoDiagnostic, Result};
+
+use crate::config::{Layout, Split, Window};
+
+pub fn list_sessions() -> Vec<String> {
+    let output = match zellij()
+        .args(["list-sessions", "--short", "--no-formatting"])
+        .output()
+    {
+        Ok(o) => o,
+        Err(_) => return Vec::new(),
+    };
+
+    String::from_utf8(output.stdout)
+        .map(|s| s.lines().map(|s| s.trim().to_string()).collect_vec())
+        .unwrap_or_default()
+}
+
+pub fn session_exists(name: &str) -> bool {
+    match name.split_once(':') {
+        Some((session, tab)) => action_output(Some(session), &["query-tab-names"])
+            .map(|tabs| tabs.lines().any(|t| t.trim() == tab))
+            .unwrap_or(false),
+        None => list_sessions().iter().any(|s| s == name),
+    }
+}
+
+pub fn create_session(
+    name: &str,
+    path: &Path,
+    window_name: Option<&str>,
+    layout: Option<&Layout>,
+    env: &IndexMap<String, String>,
+) -> Result<()> {
+    let status = zellij()
+        .args(["attach", "--create-background", name])
+        .current_dir(path)
+        .envs(env)
+        .stdin(Stdio::null())
+        .status()
+        .into_diagnostic()?;
+    if !status.success() {
+        return Err(miette!("failed to create zellij session '{}'", name));
+    }
+
+    let windows = layout.map(|l| l.windows.as_slice()).unwrap_or_default();
+    let first_name = windows
+        .first()
+        .and_then(|w| w.name.as_deref())
+        .or(window_name);
+    if let Some(tab) = first_name {
+        action(Some(name), &["rename-tab", tab])?;
+    }
+
+    for (index, window) in windows.iter().enumerate() {
+        if index > 0 {
+            let mut args = vec!["new-tab", "--cwd", path.to_str().unwrap_or(".")];
+            if let Some(tab) = window.name.as_deref() {
+                args.extend(["--name", tab]);
+            }
+            action(Some(name), &args)?;
+        }
+        apply_window(name, window, path)?;
+    }
+
+    Ok(())
+}
+
+/// Create the panes described by the layout window in the focused tab and send each pane its
+/// startup command.
+fn apply_window(session: &str, window: &Window, path: &Path) -> Result<()> {
+    for (index, pane) in window.panes.iter().enumerate() {
+        if index > 0 {
+            split_window(session, pane.split, path)?;
+        }
+
+        if let Some(cmd) = pane.command.as_deref() {
+            write_command(Some(session), cmd)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Attach to the session, or switch the current client to it when running inside of zellij
+pub fn attach_session(name: &str) -> Result<()> {
+    if in_zellij() {
+        return action(None, &["switch-session", name]).map_err(|e| {
+            miette!(
+                "failed to switch to zellij session '{}', switching sessions from inside of \
+                 zellij requires a zellij version with the 'switch-session' action: {}",
+                name,
+                e
+            )
+        });
+    }
+
+    zellij().args(["attach", name]).status().into_diagnostic()?;
+    Ok(())
+}
+
+pub fn kill_session(name: &str) -> Result<()> {
+    let output = zellij()
+        .args(["kill-session", name])
+        .output()
+        .into_diagnostic()?;
+    if !output.status.success() {
+        return Err(miette!(
+            "failed to kill session '{}': {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+pub fn rename_session(name: &str, new_name: &str) -> Result<()> {
+    let output = action_command(Some(name), &["rename-session", new_name])
+        .output()
+        .into_diagnostic()?;
+    if !output.status.success() {
+        return Err(miette!(
+            "failed to rename session '{}': {}",
+            name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+pub fn list_windows(session: &str) -> Vec<String> {
+    action_output(Some(session), &["query-tab-names"])
+        .map(|s| s.lines().map(|s| s.trim().to_string()).collect_vec())
+        .unwrap_or_default()
+}
+
+pub fn create_window(name: &str, path: Option<&Path>) -> Result<()> {
+    let mut args = vec!["new-tab", "--name", name];
+    if let Some(path) = path.and_then(|p| p.to_str()) {
+        args.extend(["--cwd", path]);
+    }
+    action(None, &args)
+}
+
+pub fn send_command(name: &str, command: &str) -> Result<()> {
+    let session = match name.split_once(':') {
+        Some((session, tab)) => {
+            action(Some(session), &["go-to-tab-name", tab])?;
+            session
+        }
+        None => name,
+    };
+    write_command(Some(session), command)
+}
+
+/// Zellij does not expose pane ids to the cli, the new pane is created next to the focused pane
+/// of `target` and the session name is returned as the target of the new pane.
+pub fn split_window(target: &str, split: Split, path: &Path) -> Result<String> {
+    let session = target.split_once(':').map_or(target, |(s, _)| s);
+    let direction = match split {
+        Split::Horizontal => "right",
+        Split::Vertical => "down",
+    };
+    action(
+        Some(session),
+        &[
+            "new-pane",
+            "--direction",
+            direction,
+            "--cwd",
+            path.to_str().unwrap_or("."),
+        ],
+    )?;
+    Ok(session.to_string())
+}
+
+pub fn capture_pane(target: &str) -> Result<String> {
+    let session = target.split_once(':').map_or(target, |(s, _)| s);
+    let file = std::env::temp_dir().join(format!("tux-zellij-{}.dump", std::process::id()));
+    action(Some(session), &["dump-screen", &file.to_string_lossy()])?;
+    let content = std::fs::read_to_string(&file).into_diagnostic();
+    let _ = std::fs::remove_file(&file);
+    content
+}
+
+pub fn set_environment(_session: &str, _key: &str, _value: &str) -> Result<()> {
+    Err(miette!(
+        "zellij does not support setting environment variables of a running session"
+    ))
+}
+
+/// Zellij has no session options to store the path in. Sessions are matched by name instead.
+pub fn set_session_path(_session: &str, _path: &Path) -> Result<()> {
+    Ok(())
+}
+
+pub fn session_name() -> Option<String> {
+    std::env::var("ZELLIJ_SESSION_NAME").ok()
+}
+
+pub fn in_zellij() -> bool {
+    std::env::var("ZELLIJ").is_ok()
+}
+
+fn write_command(session: Option<&str>, command: &str) -> Result<()> {
+    action(session, &["write-chars", command])?;
+    // Carriage return to execute the command
+    action(session, &["write", "13"])
+}
+
+fn zellij() -> Command {
+    Command::new("zellij")
+}
+
+fn action_command(session: Option<&str>, args: &[&str]) -> Command {
+    let mut command = zellij();
+    if let Some(session) = session {
+        command.args(["--session", session]);
+    }
+    command.arg("action").args(args);
+    command
+}
+
+fn action(session: Option<&str>, args: &[&str]) -> Result<()> {
+    let output = action_command(session, args).output().into_diagnostic()?;
+    if !output.status.success() {
+        return Err(miette!(
+            "zellij action '{}' failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+fn action_output(session: Option<&str>, args: &[&str]) -> Result<String> {
+    let output = action_command(session, args).output().into_diagnostic()?;
+    String::from_utf8(output.stdout).into_diagnostic()
+}
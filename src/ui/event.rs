@@ -1,4 +1,4 @@
-use crossterm::event::{self, Event as CrosstermEvent, KeyEvent};
+use crossterm::event::{self, Event as CrosstermEvent, KeyEvent, MouseEvent};
 use std::{
     sync::mpsc,
     thread,
@@ -7,10 +7,15 @@ use std::{
 
 use miette::{IntoDiagnostic, Result};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum Event {
     Tick,
     Key(KeyEvent),
+    Mouse(MouseEvent),
+    /// The terminal was resized
+    Resize,
+    /// Text pasted while bracketed paste is enabled
+    Paste(String),
 }
 
 pub struct EventHandler {
@@ -39,6 +44,9 @@ impl EventHandler {
                                     Ok(())
                                 }
                             }
+                            Ok(CrosstermEvent::Mouse(e)) => sender.send(Event::Mouse(e)),
+                            Ok(CrosstermEvent::Resize(..)) => sender.send(Event::Resize),
+                            Ok(CrosstermEvent::Paste(text)) => sender.send(Event::Paste(text)),
                             _ => Ok(()),
                         };
                     }
@@ -0,0 +1,45 @@
+use std::process::Command;
+
+use crate::config::{Finder, Mode};
+
+/// Command running the external `finder`, or `None` for the built-in picker. The prompt, query,
+/// multi selection, exact matching and height are only passed to fzf and skim, custom commands
+/// are run as is.
+pub fn command(
+    finder: &Finder,
+    prompt: &str,
+    query: &str,
+    multi: bool,
+    exact: bool,
+    mode: Mode,
+) -> Option<Command> {
+    let program = match finder {
+        Finder::Builtin => return None,
+        Finder::Fzf => "fzf",
+        Finder::Skim => "sk",
+        Finder::Command(command) => {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", command]);
+            return Some(cmd);
+        }
+    };
+
+    let mut cmd = Command::new(program);
+    cmd.args(["--prompt", prompt, "--query", query]);
+    if multi {
+        cmd.arg("--multi");
+    }
+    if exact {
+        cmd.arg("--exact");
+    }
+    match mode {
+        Mode::Full => {}
+        Mode::Lines(lines) => {
+            cmd.args(["--height", &lines.to_string()]);
+        }
+        Mode::Percentage(percentage) => {
+            cmd.args(["--height", &format!("{}%", (percentage * 100.0).round())]);
+        }
+    }
+    Some(cmd)
+}
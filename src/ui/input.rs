@@ -0,0 +1,211 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Longest query the prompt accepts in bytes
+const MAX_LEN: usize = u16::MAX as usize;
+
+/// Text of the picker prompt and the position of the cursor in it. The cursor is a byte offset
+/// that always sits on a grapheme cluster boundary, so accented characters, emoji and CJK text
+/// are moved over and deleted as a whole.
+#[derive(Debug, Default, Clone)]
+pub struct Input {
+    text: String,
+    cursor: usize,
+}
+
+impl Input {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Replace the text and move the cursor to its end
+    pub fn set(&mut self, text: String) {
+        self.text = text;
+        self.cursor = self.text.len();
+    }
+
+    /// Columns taken up on screen by the text before the cursor
+    pub fn cursor_width(&self) -> usize {
+        self.text[..self.cursor].width()
+    }
+
+    /// Insert `text` at the cursor. Returns false if the input is full.
+    pub fn insert(&mut self, text: &str) -> bool {
+        if text.is_empty() || self.text.len() + text.len() > MAX_LEN {
+            return false;
+        }
+
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+        // A combining character joins the grapheme before it
+        self.cursor = self.boundary_at_or_after(self.cursor);
+        true
+    }
+
+    /// Delete the grapheme before the cursor. Returns false if there was nothing to delete.
+    pub fn backspace(&mut self) -> bool {
+        self.delete_range(self.prev_boundary(), self.cursor)
+    }
+
+    /// Delete the grapheme under the cursor. Returns false if there was nothing to delete.
+    pub fn delete(&mut self) -> bool {
+        self.delete_range(self.cursor, self.next_boundary())
+    }
+
+    /// Remove the bytes `start..end` and move the cursor to `start`. Both ends have to be on a
+    /// grapheme boundary. Returns false if the range is empty.
+    pub fn delete_range(&mut self, start: usize, end: usize) -> bool {
+        if start >= end {
+            return false;
+        }
+
+        self.text.replace_range(start..end, "");
+        self.cursor = start;
+        true
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn move_to(&mut self, cursor: usize) {
+        self.cursor = self.boundary_at_or_after(cursor.min(self.text.len()));
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.prev_boundary();
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = self.next_boundary();
+    }
+
+    /// Start of the word before the cursor. Words are separated by whitespace and `/` so path
+    /// components are removed one at a time.
+    pub fn word_start(&self) -> usize {
+        self.text[..self.cursor]
+            .trim_end_matches(is_word_separator)
+            .trim_end_matches(|c| !is_word_separator(c))
+            .len()
+    }
+
+    /// End of the word after the cursor
+    pub fn word_end(&self) -> usize {
+        let rest = self.text[self.cursor..]
+            .trim_start_matches(is_word_separator)
+            .trim_start_matches(|c| !is_word_separator(c));
+        self.text.len() - rest.len()
+    }
+
+    fn prev_boundary(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.cursor, |g| self.cursor + g.len())
+    }
+
+    fn boundary_at_or_after(&self, offset: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .map(|(i, _)| i)
+            .chain(std::iter::once(self.text.len()))
+            .find(|&i| i >= offset)
+            .unwrap_or(self.text.len())
+    }
+}
+
+fn is_word_separator(c: char) -> bool {
+    c.is_whitespace() || c == '/'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Input;
+
+    fn input(text: &str) -> Input {
+        let mut input = Input::default();
+        input.set(text.to_string());
+        input
+    }
+
+    #[test]
+    fn insert_at_cursor() {
+        let mut input = input("~/src");
+        input.move_to(1);
+        assert!(input.insert("/code"));
+        assert_eq!(input.text(), "~/code/src");
+        assert_eq!(input.cursor(), 6);
+        assert!(!input.insert(""));
+    }
+
+    #[test]
+    fn insert_combining_mark() {
+        let mut input = input("cafe");
+        assert!(input.insert("\u{301}"));
+        assert_eq!(input.text(), "cafe\u{301}");
+        assert_eq!(input.cursor(), input.text().len());
+        input.move_left();
+        assert_eq!(input.cursor(), 3);
+    }
+
+    #[test]
+    fn backspace_grapheme() {
+        let mut input = input("cafe\u{301}");
+        assert!(input.backspace());
+        assert_eq!(input.text(), "caf");
+        input.move_to(0);
+        assert!(!input.backspace());
+        assert!(input.delete());
+        assert_eq!(input.text(), "af");
+    }
+
+    #[test]
+    fn move_by_words() {
+        let mut input = input("~/src/tux mux");
+        assert_eq!(input.word_start(), 10);
+        input.move_to(10);
+        assert_eq!(input.word_start(), 6);
+        input.move_to(9);
+        assert_eq!(input.word_start(), 6);
+        input.move_to(2);
+        assert_eq!(input.word_end(), 5);
+        input.move_to(5);
+        assert_eq!(input.word_end(), 9);
+        input.move_to(0);
+        assert_eq!(input.word_start(), 0);
+    }
+
+    #[test]
+    fn cursor_width_of_combining_sequence() {
+        let input = input("cafe\u{301}");
+        assert_eq!(input.cursor_width(), 4);
+    }
+
+    #[test]
+    fn cursor_width_of_cjk() {
+        let mut input = input("프로젝트");
+        assert_eq!(input.cursor_width(), 8);
+        input.move_left();
+        assert_eq!(input.cursor_width(), 6);
+    }
+
+    #[test]
+    fn cursor_width_of_emoji_zwj_sequence() {
+        let mut input = input("a👩\u{200d}💻");
+        assert_eq!(input.cursor_width(), 3);
+        input.move_left();
+        assert_eq!(input.cursor(), 1);
+        assert_eq!(input.cursor_width(), 1);
+    }
+}
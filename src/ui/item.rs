@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+/// Item of the picker, the unified picker lists running sessions next to workspaces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Session(String),
+    Workspace(PathBuf),
+}
+
+impl Item {
+    /// Kind of the picker item `item`. It is a session if it is one of the listed sessions
+    /// `names`, which are only listed by the unified picker.
+    pub fn new(item: &str, names: &[String]) -> Self {
+        match names.iter().any(|name| name == item) {
+            true => Item::Session(item.to_string()),
+            false => Item::Workspace(PathBuf::from(item)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::Item;
+
+    #[test]
+    fn kind_of_listed_sessions() {
+        let names = vec!["api".to_string(), "src/web".to_string()];
+        assert_eq!(Item::new("api", &names), Item::Session("api".to_string()));
+        assert_eq!(
+            Item::new("src/web", &names),
+            Item::Session("src/web".to_string())
+        );
+        assert_eq!(
+            Item::new("/src/api", &names),
+            Item::Workspace(PathBuf::from("/src/api"))
+        );
+        assert_eq!(Item::new("api", &[]), Item::Workspace(PathBuf::from("api")));
+    }
+}
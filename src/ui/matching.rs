@@ -0,0 +1,112 @@
+use std::fmt;
+
+use nucleo::{
+    pattern::{CaseMatching, Normalization, Pattern},
+    Matcher, Utf32Str,
+};
+
+use super::{picker::contract_home, regex::Regex};
+
+/// How the query of the picker is matched against the items.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MatchMode {
+    #[default]
+    Fuzzy,
+    /// Every word of the query is a substring of the item
+    Exact,
+    /// The item starts with the query
+    Prefix,
+    /// The query is a regular expression found in the item
+    Regex,
+}
+
+impl MatchMode {
+    /// Mode selected after this one when cycling through the modes
+    pub fn next(self) -> Self {
+        match self {
+            MatchMode::Fuzzy => MatchMode::Exact,
+            MatchMode::Exact => MatchMode::Prefix,
+            MatchMode::Prefix => MatchMode::Regex,
+            MatchMode::Regex => MatchMode::Fuzzy,
+        }
+    }
+
+    /// Query in the pattern syntax of the fuzzy matcher. Regular expressions are not supported
+    /// by the fuzzy matcher so every item is matched and filtered by the picker instead.
+    pub fn pattern(self, query: &str) -> String {
+        match self {
+            MatchMode::Fuzzy => query.to_string(),
+            MatchMode::Exact => query
+                .split_whitespace()
+                .map(|word| match word.starts_with(['\'', '^', '!']) {
+                    true => word.to_string(),
+                    false => format!("'{}", word),
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            MatchMode::Prefix if query.is_empty() => String::new(),
+            MatchMode::Prefix => format!("^{}", query.replace(' ', "\\ ")),
+            MatchMode::Regex => String::new(),
+        }
+    }
+
+    /// Items matching `query` the same way they would in the picker. Like in the picker the
+    /// home directory at the start of an item is matched as `~`.
+    pub fn filter<'a>(self, query: &str, items: &'a [String]) -> Vec<&'a String> {
+        let columns = items.iter().map(|item| (item, contract_home(item)));
+        if self == MatchMode::Regex {
+            return match Regex::new(query) {
+                Some(regex) => columns
+                    .filter(|(_, column)| regex.find(column).is_some())
+                    .map(|(item, _)| item)
+                    .collect(),
+                None => vec![],
+            };
+        }
+
+        let pattern = Pattern::parse(
+            &self.pattern(query),
+            CaseMatching::Smart,
+            Normalization::Smart,
+        );
+        let mut matcher = Matcher::new(nucleo::Config::DEFAULT);
+        let mut buf = vec![];
+        columns
+            .filter(|(_, column)| {
+                pattern
+                    .score(Utf32Str::new(column, &mut buf), &mut matcher)
+                    .is_some()
+            })
+            .map(|(item, _)| item)
+            .collect()
+    }
+}
+
+impl fmt::Display for MatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MatchMode::Fuzzy => write!(f, "fuzzy"),
+            MatchMode::Exact => write!(f, "exact"),
+            MatchMode::Prefix => write!(f, "prefix"),
+            MatchMode::Regex => write!(f, "regex"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MatchMode;
+
+    #[test]
+    fn filter_home_as_tilde() {
+        let home = dirs_next::home_dir().unwrap();
+        let items = vec![
+            home.join("src/api").display().to_string(),
+            "/srv/src/web".to_string(),
+        ];
+
+        assert_eq!(MatchMode::Prefix.filter("~/src", &items), [&items[0]]);
+        assert_eq!(MatchMode::Regex.filter("^~/.*api$", &items), [&items[0]]);
+        assert_eq!(MatchMode::Exact.filter("src", &items).len(), 2);
+    }
+}
@@ -1,5 +1,14 @@
 mod event;
+mod finder;
+mod input;
+mod item;
+mod matching;
 mod picker;
+mod preview;
+mod regex;
 mod tui;
 
-pub use picker::Picker;
+pub use item::Item;
+pub use matching::MatchMode;
+pub use picker::{Picker, PickerOutcome};
+pub use preview::{PreviewProvider, SessionPreview, UnifiedPreview, WorkspacePreview};
@@ -1,36 +1,126 @@
-use crossterm::event::{KeyEvent, KeyModifiers};
-use miette::{IntoDiagnostic, Result};
+use crossterm::event::{KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use miette::{miette, IntoDiagnostic, Result};
 
-use std::sync::Arc;
-use std::time::Duration;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 
 use crossterm::event::KeyCode;
+use indexmap::IndexSet;
 use nucleo::{
     pattern::{CaseMatching, Normalization},
-    Injector, Nucleo,
+    Injector, Matcher, Nucleo,
 };
 use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout},
-    style::{Color, Style, Stylize},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
     text::{Line, Span},
     widgets::{
         block::Position, Block, Borders, HighlightSpacing, List, ListDirection, ListItem,
         ListState, Paragraph,
     },
-    Frame, Terminal,
+    Frame,
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::{
+    config::{Action, Finder, Keys, Mode, Preview, PreviewPosition, Theme},
+    frecency::Frecency,
+    history::History,
 };
 
 use super::event::{Event, EventHandler};
+use super::finder;
+use super::input::Input;
+use super::matching::MatchMode;
+use super::preview::PreviewProvider;
+use super::regex::Regex;
 use super::tui::Tui;
 
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+/// Score added per unit of `ln(1 + frecency)`. A match scores 16 per matched character so a
+/// frequently used workspace can outrank a slightly better match.
+const FRECENCY_WEIGHT: f64 = 16.0;
+/// Longest time between the clicks of a double click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Handler of an action on items. Returns the new items of the picker if they changed.
+type ItemHandler = Box<dyn FnMut(&[String]) -> Result<Option<Vec<String>>>>;
+/// Handler of renaming an item. Returns the new items of the picker if they changed.
+type RenameHandler = Box<dyn FnMut(&str, &str) -> Result<Option<Vec<String>>>>;
+/// Injector of the picker shared with its refresh. Replacing the items swaps the injector, so
+/// the refresh has to push through the lock to reach the current one.
+pub type SharedInjector = Arc<Mutex<Injector<String>>>;
+
+/// Items the picker was closed with and the action that closed it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PickerOutcome {
+    /// [`Action::Accept`], [`Action::AcceptAndKill`] or [`Action::OpenWindow`]
+    pub action: Action,
+    /// The marked items in the order they were marked, or the item under the cursor. Never
+    /// empty and only ever a single item for [`Picker::select`].
+    pub items: Vec<String>,
+    /// The item under the cursor, which is not necessarily one of the marked items
+    pub current: String,
+}
+
 pub struct Picker {
-    pub injector: Injector<String>,
+    pub injector: SharedInjector,
     matcher: Nucleo<String>,
+    refresh: Option<JoinHandle<Option<Vec<String>>>>,
+    started: Instant,
+    /// Frecency bonus of each item
+    bonus: HashMap<String, u32>,
+    /// Text shown after an item
+    notes: HashMap<String, String>,
+    /// Indices into the matched items of the snapshot in display order
+    ranked: Vec<u32>,
+    /// Number of matched items when the items were last ranked
+    ranked_count: usize,
+    rerank: bool,
+    scorer: Matcher,
     selection: ListState,
-    filter: String,
-    cursor_pos: u16,
+    /// Area the items were last drawn in, without the border
+    items_area: Rect,
+    /// Time and item of the last click to detect double clicks
+    last_click: Option<(Instant, usize)>,
+    multi: bool,
+    marked: IndexSet<String>,
+    preview: Option<Arc<dyn PreviewProvider>>,
+    preview_config: Preview,
+    show_preview: bool,
+    /// Item the preview lines were generated for
+    preview_item: Option<String>,
+    preview_lines: Vec<String>,
+    preview_job: Option<(String, JoinHandle<Vec<String>>)>,
+    input: Input,
+    history: Option<History>,
+    /// Entry of the history shown in the prompt while browsing the history
+    history_index: Option<usize>,
+    /// Query typed before browsing the history
+    history_draft: String,
     prompt: String,
+    mode: Mode,
+    finder: Finder,
+    match_mode: MatchMode,
+    regex: Option<Regex>,
+    keys: Keys,
+    theme: Theme,
+    on_kill: Option<ItemHandler>,
+    on_rename: Option<RenameHandler>,
+    /// Close the picker on [`Action::OpenWindow`]
+    open_window: bool,
+    /// Item being renamed in the prompt and the query to restore afterwards
+    renaming: Option<(String, Input)>,
+    /// Error of the last action, shown in place of the counter until the next key
+    message: Option<String>,
     should_exit: bool,
 }
 
@@ -46,21 +136,166 @@ impl Picker {
         let injector = matcher.injector();
 
         Picker {
-            injector,
+            injector: Arc::new(Mutex::new(injector)),
             matcher,
+            refresh: None,
+            started: Instant::now(),
+            bonus: HashMap::new(),
+            notes: HashMap::new(),
+            ranked: Vec::new(),
+            ranked_count: 0,
+            rerank: false,
+            scorer: Matcher::new(nucleo::Config::DEFAULT),
             selection: ListState::default(),
-            filter: String::default(),
-            cursor_pos: 0,
+            items_area: Rect::default(),
+            last_click: None,
+            multi: false,
+            marked: IndexSet::new(),
+            preview: None,
+            preview_config: Preview::default(),
+            show_preview: false,
+            preview_item: None,
+            preview_lines: Vec::new(),
+            preview_job: None,
+            input: Input::default(),
+            history: None,
+            history_index: None,
+            history_draft: String::new(),
             prompt: String::default(),
+            mode: Mode::Full,
+            finder: Finder::Builtin,
+            match_mode: MatchMode::default(),
+            regex: None,
+            keys: Keys::default(),
+            theme: Theme::default().or_no_color(),
+            on_kill: None,
+            on_rename: None,
+            open_window: false,
+            renaming: None,
+            message: None,
             should_exit: false,
         }
     }
 
     pub fn items(self, list: &[String]) -> Self {
+        let injector = lock(&self.injector);
         for str in list {
-            self.injector
-                .push(str.to_owned(), |_, dst| dst[0] = str.to_owned().into());
+            Self::push(&injector, str.to_owned());
         }
+        drop(injector);
+        self
+    }
+
+    /// Add an item to the picker through one of its injectors. Items are matched and shown with
+    /// the home directory replaced by `~` but the full item is returned when selected.
+    pub fn push(injector: &Injector<String>, item: String) {
+        injector.push(item, |item, dst| dst[0] = contract_home(item).into());
+    }
+
+    /// Run `f` on a background thread while the picker is open. Items added through the given
+    /// injector show up as they are pushed and a spinner is shown until `f` returns. If `f`
+    /// returns a list then it replaces all items in the picker. The injector is locked while a
+    /// kill or rename handler runs and its items replace the current ones.
+    pub fn refresh<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(SharedInjector) -> Option<Vec<String>> + Send + 'static,
+    {
+        let injector = self.injector.clone();
+        self.refresh = Some(thread::spawn(move || f(injector)));
+        self
+    }
+
+    /// Rank items that were attached to often and recently higher
+    pub fn frecency(mut self, frecency: &Frecency) -> Self {
+        self.bonus = frecency
+            .0
+            .keys()
+            .map(|path| {
+                let bonus = frecency.score(path).ln_1p() * FRECENCY_WEIGHT;
+                (path.to_owned(), bonus as u32)
+            })
+            .collect();
+        self
+    }
+
+    /// Show a note after each item in `notes`, such as the window count of a session. Notes are
+    /// not matched against the query.
+    pub fn notes(mut self, notes: HashMap<String, String>) -> Self {
+        self.notes = notes;
+        self
+    }
+
+    /// Show the output of `provider` for the item under the cursor. The preview is toggled with
+    /// `ctrl-t`.
+    pub fn preview<P: PreviewProvider + 'static>(mut self, provider: P, config: &Preview) -> Self {
+        self.preview = Some(Arc::new(provider));
+        self.preview_config = *config;
+        self.show_preview = config.visible;
+        self
+    }
+
+    /// Height of the picker. Anything other than [`Mode::Full`] draws the picker below the
+    /// cursor instead of on the alternate screen.
+    pub fn height(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Pick with an external finder such as fzf instead of the built-in picker
+    pub fn finder(mut self, finder: &Finder) -> Self {
+        self.finder = finder.clone();
+        self
+    }
+
+    /// Initial match mode. It can be changed in the picker with `alt-r`.
+    pub fn match_mode(mut self, mode: MatchMode) -> Self {
+        self.match_mode = mode;
+        self
+    }
+
+    /// Key chords bound to the actions of the picker
+    pub fn keys(mut self, keys: &Keys) -> Self {
+        self.keys = keys.clone();
+        self
+    }
+
+    /// Colors of the picker. Ignored if `NO_COLOR` is set.
+    pub fn theme(mut self, theme: &Theme) -> Self {
+        self.theme = theme.or_no_color();
+        self
+    }
+
+    /// Browse `history` from the prompt and record the query when an item is accepted
+    pub fn history(mut self, history: History) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Kill the marked items or the item under the cursor with `f` on [`Action::Kill`] without
+    /// closing the picker. The items returned by `f` replace the items of the picker.
+    pub fn on_kill<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&[String]) -> Result<Option<Vec<String>>> + 'static,
+    {
+        self.on_kill = Some(Box::new(f));
+        self
+    }
+
+    /// Edit the name of the item under the cursor in the prompt on [`Action::Rename`] and pass
+    /// the old and the new name to `f` once it is confirmed. The items returned by `f` replace
+    /// the items of the picker.
+    pub fn on_rename<F>(mut self, f: F) -> Self
+    where
+        F: FnMut(&str, &str) -> Result<Option<Vec<String>>> + 'static,
+    {
+        self.on_rename = Some(Box::new(f));
+        self
+    }
+
+    /// Close the picker with [`Action::OpenWindow`] when the item under the cursor is opened as
+    /// a window. The action does nothing otherwise.
+    pub fn open_window(mut self) -> Self {
+        self.open_window = true;
         self
     }
 
@@ -71,130 +306,646 @@ impl Picker {
 
     pub fn filter(mut self, query: Option<&str>) -> Self {
         if let Some(filter) = query {
-            self.filter = filter.to_string();
+            self.input.set(filter.to_string());
         }
         self
     }
 
-    pub fn select(mut self) -> Result<Option<String>> {
-        let backend = CrosstermBackend::new(std::io::stderr());
-        let terminal = Terminal::new(backend).into_diagnostic()?;
+    /// Select a single item. Returns `None` if the picker was cancelled.
+    pub fn select(mut self) -> Result<Option<PickerOutcome>> {
+        self.pick()
+    }
+
+    /// Select any number of items. Tab and Shift-Tab mark the item under the cursor. The outcome
+    /// holds the marked items in the order they were marked, or the item under the cursor if
+    /// none are marked.
+    pub fn select_many(mut self) -> Result<Option<PickerOutcome>> {
+        self.multi = true;
+        self.pick()
+    }
+
+    /// Run the external finder or the built-in picker. External finders always accept.
+    fn pick(&mut self) -> Result<Option<PickerOutcome>> {
+        let Some(command) = self.finder_command() else {
+            return self.run();
+        };
+
+        let mut items = self.run_finder(command)?;
+        if !self.multi {
+            items.truncate(1);
+        }
+        Ok(items.first().cloned().map(|current| PickerOutcome {
+            action: Action::Accept,
+            items,
+            current,
+        }))
+    }
+
+    fn finder_command(&self) -> Option<Command> {
+        finder::command(
+            &self.finder,
+            &self.prompt,
+            self.input.text(),
+            self.multi,
+            self.match_mode == MatchMode::Exact,
+            self.mode,
+        )
+    }
+
+    /// Write the items to the stdin of the external finder and read the selected items from its
+    /// stdout. Items found by a running refresh are passed on as they arrive.
+    fn run_finder(&mut self, mut command: Command) -> Result<Vec<String>> {
+        let program = command.get_program().to_string_lossy().to_string();
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| miette!("failed to run finder '{}': {}", program, e))?;
+
+        let mut stdin = BufWriter::new(child.stdin.take().expect("stdin is piped"));
+        let mut written = HashSet::new();
+        let mut next = 0;
+        'feed: loop {
+            let done = self.refresh.is_none();
+            let status = self.matcher.tick(10);
+            let snapshot = self.matcher.snapshot();
+            for item in (next..snapshot.item_count()).filter_map(|i| snapshot.get_item(i)) {
+                // The finder exited, stop writing items
+                if written.insert(item.data.clone()) && writeln!(stdin, "{}", item.data).is_err() {
+                    break 'feed;
+                }
+            }
+            next = snapshot.item_count();
+            if stdin.flush().is_err() || (done && !status.running) {
+                break;
+            }
+
+            if self.refresh.as_ref().is_some_and(|h| h.is_finished()) {
+                // Replaced items are restarted from the beginning, the set skips known items
+                self.poll_refresh();
+                next = 0;
+            } else {
+                thread::sleep(Duration::from_millis(15));
+            }
+        }
+        drop(stdin);
+
+        let output = child.wait_with_output().into_diagnostic()?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.to_string())
+            .collect())
+    }
+
+    fn run(&mut self) -> Result<Option<PickerOutcome>> {
         let events = EventHandler::new(Duration::from_millis(15));
-        let mut tui = Tui::new(terminal, events);
+        let mut tui = Tui::new(self.mode, events)?;
         tui.enter()?;
 
-        if !self.filter.is_empty() {
-            self.cursor_pos = self.filter.len() as u16;
-            self.update_matcher_pattern("");
+        if !self.input.is_empty() {
+            self.update_matcher_pattern(false);
         }
 
-        let mut selection = None;
+        let mut outcome = None;
         while !self.should_exit {
-            tui.draw(&mut self)?;
-            selection = match tui.events.next()? {
+            tui.draw(self)?;
+            outcome = match tui.events.next()? {
                 Event::Tick => None,
                 Event::Key(key_event) => self.update(key_event),
+                Event::Paste(text) => {
+                    self.insert_text(&text);
+                    None
+                }
+                Event::Mouse(mouse_event) => self.update_mouse(mouse_event),
+                Event::Resize => {
+                    tui.resize()?;
+                    None
+                }
             };
+            self.poll_refresh();
+            self.poll_preview();
         }
 
         tui.exit()?;
-        Ok(selection)
+        if let Some(history) = self.history.as_mut().filter(|_| outcome.is_some()) {
+            history.add(self.input.text());
+            history.write()?;
+        }
+        Ok(outcome)
     }
 
-    fn update(&mut self, key_event: KeyEvent) -> Option<String> {
-        match key_event.code {
-            KeyCode::Esc => self.should_exit = true,
-            KeyCode::Enter => {
-                if let Some(selection) = self.get_selected_text() {
-                    self.should_exit = true;
-                    return Some(selection);
+    fn update(&mut self, key_event: KeyEvent) -> Option<PickerOutcome> {
+        self.message = None;
+        let Some(action) = self.keys.get(key_event) else {
+            match key_event.code {
+                KeyCode::Backspace => self.backspace(),
+                KeyCode::Delete => self.delete(),
+                KeyCode::Char(c)
+                    if !key_event
+                        .modifiers
+                        .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+                {
+                    self.insert_text(c.encode_utf8(&mut [0; 4]))
                 }
+                _ => {}
             }
-            KeyCode::Backspace => self.backspace(),
-            KeyCode::Delete => self.delete(),
-            KeyCode::Up => self.move_cursor_up(),
-            KeyCode::Down => self.move_cursor_down(),
-            KeyCode::Left => self.move_cursor_left(),
-            KeyCode::Right => self.move_cursor_right(),
-            _ => {
-                if let KeyCode::Char(c) = key_event.code {
-                    if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-                        match c {
-                            'c' | 'd' | 'z' => self.should_exit = true,
-                            'p' => self.move_cursor_up(),
-                            'n' => self.move_cursor_down(),
-                            'b' | 'h' => self.move_cursor_left(),
-                            'f' | 'l' => self.move_cursor_right(),
-                            _ => {}
-                        }
-                    } else {
-                        self.update_filter(c)
-                    }
+            return None;
+        };
+
+        // Only the name can be edited while renaming
+        if self.renaming.is_some() && !action.edits_query() {
+            match action {
+                Action::Accept => self.rename(),
+                Action::Cancel => self.stop_renaming(),
+                _ => {}
+            }
+            return None;
+        }
+
+        match action {
+            Action::Up => self.move_cursor_up(),
+            Action::Down => self.move_cursor_down(),
+            Action::PageUp => self.move_selection(self.page_height() as isize),
+            Action::PageDown => self.move_selection(-(self.page_height() as isize)),
+            Action::First => self.move_selection(isize::MIN),
+            Action::Last => self.move_selection(isize::MAX),
+            Action::BackwardChar => self.input.move_left(),
+            Action::ForwardChar => self.input.move_right(),
+            Action::BackwardWord => self.input.move_to(self.input.word_start()),
+            Action::ForwardWord => self.input.move_to(self.input.word_end()),
+            Action::BeginningOfLine => self.input.move_to(0),
+            Action::EndOfLine => self.input.move_to(self.input.text().len()),
+            Action::BackwardKillWord => {
+                self.delete_range(self.input.word_start(), self.input.cursor())
+            }
+            Action::KillWord => self.delete_range(self.input.cursor(), self.input.word_end()),
+            Action::BackwardKillLine => self.delete_range(0, self.input.cursor()),
+            Action::KillLine => self.delete_range(self.input.cursor(), self.input.text().len()),
+            Action::PreviousHistory => self.browse_history(-1),
+            Action::NextHistory => self.browse_history(1),
+            Action::TogglePreview => self.show_preview = !self.show_preview,
+            Action::ToggleSelect if self.multi => {
+                self.toggle_mark();
+                self.move_cursor_up();
+            }
+            Action::ToggleSelectPrevious if self.multi => {
+                self.toggle_mark();
+                self.move_cursor_down();
+            }
+            Action::ToggleSelect | Action::ToggleSelectPrevious => {}
+            Action::CycleMatchMode => {
+                self.match_mode = self.match_mode.next();
+                self.update_matcher_pattern(false);
+            }
+            Action::Accept | Action::AcceptAndKill => return self.accept(action),
+            Action::Kill => self.kill(),
+            Action::Rename => self.start_renaming(),
+            Action::OpenWindow if self.open_window => return self.accept(action),
+            Action::OpenWindow => {}
+            Action::Cancel => self.should_exit = true,
+        }
+        None
+    }
+
+    /// Select the clicked item and accept it on a double click. The wheel moves the selection.
+    fn update_mouse(&mut self, mouse_event: MouseEvent) -> Option<PickerOutcome> {
+        if self.renaming.is_some() {
+            return None;
+        }
+
+        match mouse_event.kind {
+            MouseEventKind::ScrollUp => self.move_cursor_up(),
+            MouseEventKind::ScrollDown => self.move_cursor_down(),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let index = self.item_at(mouse_event.column, mouse_event.row)?;
+                let double_click = self.last_click.is_some_and(|(time, last)| {
+                    last == index && time.elapsed() <= DOUBLE_CLICK_INTERVAL
+                });
+
+                self.selection.select(Some(index));
+                if double_click {
+                    self.last_click = None;
+                    return self.accept(Action::Accept);
                 }
+                self.last_click = Some((Instant::now(), index));
             }
-        };
+            _ => {}
+        }
         None
     }
 
+    /// Index of the ranked item drawn at the given screen position
+    fn item_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = self.items_area;
+        if !area.contains((column, row).into()) {
+            return None;
+        }
+
+        // Items are drawn from the bottom of the area upwards
+        let index = self.selection.offset() + (area.bottom() - 1 - row) as usize;
+        (index < self.ranked.len()).then_some(index)
+    }
+
+    fn accept(&mut self, action: Action) -> Option<PickerOutcome> {
+        let selected = self.get_selected_text()?;
+        let items = match self.multi && !self.marked.is_empty() {
+            true => self.marked.iter().cloned().collect(),
+            false => vec![selected.clone()],
+        };
+
+        self.should_exit = true;
+        Some(PickerOutcome {
+            action,
+            items,
+            current: selected,
+        })
+    }
+
+    /// Run the kill handler on the marked items or the item under the cursor
+    fn kill(&mut self) {
+        let items = match self.marked.is_empty() {
+            true => self.get_selected_text().into_iter().collect::<Vec<_>>(),
+            false => self.marked.iter().cloned().collect(),
+        };
+        let Some(handler) = self.on_kill.as_mut().filter(|_| !items.is_empty()) else {
+            return;
+        };
+
+        // Items the refresh finds meanwhile are either seen by the handler or pushed after the
+        // items are replaced
+        let shared = self.injector.clone();
+        let mut injector = lock(&shared);
+        let result = handler(&items);
+        self.marked.clear();
+        self.handle_result(&mut injector, result);
+    }
+
+    /// Edit the name of the item under the cursor in the prompt in place of the query
+    fn start_renaming(&mut self) {
+        let Some(item) = self
+            .get_selected_text()
+            .filter(|_| self.on_rename.is_some())
+        else {
+            return;
+        };
+
+        let mut name = Input::default();
+        name.set(item.clone());
+        let query = std::mem::replace(&mut self.input, name);
+        self.renaming = Some((item, query));
+    }
+
+    fn stop_renaming(&mut self) {
+        if let Some((_, query)) = self.renaming.take() {
+            self.input = query;
+        }
+    }
+
+    /// Run the rename handler with the name in the prompt and go back to the query
+    fn rename(&mut self) {
+        let Some((item, query)) = self.renaming.take() else {
+            return;
+        };
+        let name = std::mem::replace(&mut self.input, query);
+        let name = name.text().trim();
+        let Some(handler) = self.on_rename.as_mut() else {
+            return;
+        };
+
+        if !name.is_empty() && name != item {
+            let shared = self.injector.clone();
+            let mut injector = lock(&shared);
+            let result = handler(&item, name);
+            self.handle_result(&mut injector, result);
+        }
+    }
+
+    /// Replace the items with the ones returned by an action handler or show its error
+    fn handle_result(
+        &mut self,
+        injector: &mut Injector<String>,
+        result: Result<Option<Vec<String>>>,
+    ) {
+        match result {
+            Ok(Some(items)) => self.replace_items(injector, items),
+            Ok(None) => {}
+            Err(e) => self.message = Some(e.to_string()),
+        }
+    }
+
+    /// Replace all items. Restarting the matcher disconnects `injector`, which is the locked
+    /// shared injector, so it is swapped for a new one.
+    fn replace_items(&mut self, injector: &mut Injector<String>, items: Vec<String>) {
+        self.matcher.restart(true);
+        *injector = self.matcher.injector();
+        self.marked.retain(|item| items.contains(item));
+        // The preview of an item may have changed along with the items
+        self.preview_item = None;
+        for item in items {
+            Self::push(injector, item);
+        }
+    }
+
+    fn page_height(&self) -> usize {
+        (self.items_area.height as usize).max(1)
+    }
+
     pub fn render(&mut self, frame: &mut Frame) {
-        self.matcher.tick(10);
-        let snapshot = self.matcher.snapshot();
-        let matches = snapshot
-            .matched_items(..snapshot.matched_item_count())
-            .map(|item| ListItem::new(item.data.as_str()));
+        let status = self.matcher.tick(10);
+        if status.changed
+            || self.rerank
+            || self.ranked_count != self.matcher.snapshot().matched_item_count() as usize
+        {
+            self.rank();
+        }
 
+        let snapshot = self.matcher.snapshot();
+        let matched = self.ranked.len();
         if let Some(selected) = self.selection.selected() {
-            if snapshot.matched_item_count() == 0 {
+            if matched == 0 {
                 self.selection.select(None);
-            } else if selected > snapshot.matched_item_count() as usize {
-                self.selection
-                    .select(Some(snapshot.matched_item_count() as usize - 1));
+            } else if selected >= matched {
+                self.selection.select(Some(matched - 1));
             }
-        } else if snapshot.matched_item_count() > 0 {
+        } else if matched > 0 {
             self.selection.select(Some(0));
         }
 
-        let border_color = Color::DarkGray;
-        let info_color = Color::LightYellow;
+        let theme = self.theme;
+        let match_style = Style::default().fg(theme.matched).bold();
+
+        let layout = Layout::new(
+            Direction::Vertical,
+            [
+                Constraint::Length(frame.area().height - 1),
+                Constraint::Length(1),
+            ],
+        )
+        .split(frame.area());
+
+        let list_area = if self.preview.is_some() && self.show_preview {
+            let size = self.preview_config.size;
+            let (direction, constraints, borders, list, preview) =
+                match self.preview_config.position {
+                    PreviewPosition::Right => (
+                        Direction::Horizontal,
+                        [
+                            Constraint::Percentage(100 - size),
+                            Constraint::Percentage(size),
+                        ],
+                        Borders::LEFT,
+                        0,
+                        1,
+                    ),
+                    PreviewPosition::Top => (
+                        Direction::Vertical,
+                        [
+                            Constraint::Percentage(size),
+                            Constraint::Percentage(100 - size),
+                        ],
+                        Borders::BOTTOM,
+                        1,
+                        0,
+                    ),
+                };
+            let areas = Layout::new(direction, constraints).split(layout[0]);
+
+            let lines = self
+                .preview_lines
+                .iter()
+                .map(|l| Line::raw(l.as_str()))
+                .collect::<Vec<_>>();
+            let preview_widget = Paragraph::new(lines)
+                .style(Style::default().fg(theme.preview))
+                .block(
+                    Block::default()
+                        .borders(borders)
+                        .border_style(Style::default().fg(theme.border)),
+                );
+            frame.render_widget(preview_widget, areas[preview]);
+            areas[list]
+        } else {
+            layout[0]
+        };
+
+        // Matched characters are only looked up for the rows around the visible part of the list
+        let height = list_area.height as usize;
+        let offset = self.selection.offset();
+        let visible = offset.saturating_sub(height)..offset + 2 * height;
+        let marker_width = if self.multi { 2 } else { 0 };
+        let width = (list_area.width as usize).saturating_sub(2 + marker_width);
+
+        let pattern = snapshot.pattern().column_pattern(0);
+        let mut indices = vec![];
+        let matches = self
+            .ranked
+            .iter()
+            .filter_map(|&index| snapshot.get_matched_item(index))
+            .enumerate()
+            .map(|(row, item)| {
+                // The column only holds the first char of each grapheme, combining marks are
+                // drawn from the item itself
+                let column = &item.matcher_columns[0];
+                let text = contract_home(item.data);
+                indices.clear();
+                if visible.contains(&row) {
+                    match self
+                        .regex
+                        .as_ref()
+                        .filter(|_| self.match_mode == MatchMode::Regex)
+                    {
+                        Some(regex) => {
+                            if let Some(range) = regex.find(&text) {
+                                indices.extend(grapheme_indices(&text, range));
+                            }
+                        }
+                        None => {
+                            pattern.indices(column.slice(..), &mut self.scorer, &mut indices);
+                            indices.sort_unstable();
+                            indices.dedup();
+                        }
+                    }
+                }
+
+                let mut spans = vec![];
+                if self.multi {
+                    spans.push(if self.marked.contains(item.data) {
+                        Span::from("+ ").fg(theme.marked)
+                    } else {
+                        Span::raw("  ")
+                    });
+                }
+                let note = self.notes.get(item.data);
+                let note_width = note.map_or(0, |note| note.width() + 1);
+                spans.extend(highlight(
+                    &text,
+                    &indices,
+                    width.saturating_sub(note_width),
+                    match_style,
+                ));
+                if let Some(note) = note {
+                    spans.push(Span::from(format!(" {}", note)).fg(theme.border));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect::<Vec<_>>();
 
         let table = List::new(matches)
             .direction(ListDirection::BottomToTop)
             .highlight_spacing(HighlightSpacing::Always)
             .highlight_symbol("> ")
-            .highlight_style(Style::default().fg(Color::LightBlue))
+            .highlight_style(Style::default().fg(theme.highlight))
             .block(
                 Block::default()
                     .borders(Borders::BOTTOM)
-                    .border_style(Style::default().fg(border_color))
-                    .title_style(Style::default().fg(info_color))
+                    .border_style(Style::default().fg(theme.border))
+                    .title_style(Style::default().fg(theme.counter))
                     .title_position(Position::Bottom)
-                    .title(format!(
-                        "{}/{}",
-                        snapshot.matched_item_count(),
-                        snapshot.item_count()
-                    )),
+                    .title(self.counter(matched as u32, snapshot.item_count())),
             );
 
-        let layout = Layout::new(
-            Direction::Vertical,
-            [
-                Constraint::Length(frame.area().height - 1),
-                Constraint::Length(1),
-            ],
-        )
-        .split(frame.area());
+        self.items_area = Rect {
+            height: list_area.height.saturating_sub(1),
+            ..list_area
+        };
+        frame.render_stateful_widget(table, list_area, &mut self.selection);
 
-        frame.render_stateful_widget(table, layout[0], &mut self.selection);
+        // The match mode is shown in front of the prompt unless it is the default fuzzy mode
+        let mode = match self.match_mode {
+            _ if self.renaming.is_some() => String::new(),
+            MatchMode::Fuzzy => String::new(),
+            mode => format!("{} ", mode),
+        };
+        let prompt = match self.renaming {
+            Some(_) => "rename: ",
+            None => &self.prompt,
+        };
+        let prompt_width = prompt.width();
+        let prompt = Span::from(prompt).fg(theme.prompt).bold();
+        let input_text = Span::raw(self.input.text());
+        let input_line = Line::from(vec![
+            Span::from(mode.as_str()).fg(theme.counter),
+            prompt,
+            input_text,
+        ]);
 
-        let prompt = Span::from(&self.prompt).fg(Color::LightBlue).bold();
-        let input_text = Span::raw(&self.filter);
-        let input_line = Line::from(vec![prompt, input_text]);
-        let input = Paragraph::new(vec![input_line]);
+        // Scroll a query wider than the terminal so the cursor stays visible
+        let cursor = mode.width() + prompt_width + self.input.cursor_width();
+        let scroll = (cursor + 1).saturating_sub(layout[1].width as usize);
+        let input = Paragraph::new(vec![input_line]).scroll((0, scroll as u16));
         frame.render_widget(input, layout[1]);
-        frame.set_cursor_position((
-            layout[1].x + self.cursor_pos + self.prompt.len() as u16,
-            layout[1].y,
-        ));
+        frame.set_cursor_position((layout[1].x + (cursor - scroll) as u16, layout[1].y));
+    }
+
+    fn counter(&self, matched: u32, total: u32) -> String {
+        if let Some(message) = self.message.as_ref() {
+            return message.clone();
+        }
+
+        let mut counter = format!("{}/{}", matched, total);
+        if !self.marked.is_empty() {
+            counter = format!("{} ({} selected)", counter, self.marked.len());
+        }
+
+        if self.refresh.is_none() {
+            return counter;
+        }
+
+        let frame = (self.started.elapsed().as_millis() / SPINNER_INTERVAL.as_millis()) as usize;
+        format!("{} {} scanning…", SPINNER[frame % SPINNER.len()], counter)
+    }
+
+    /// Order the matched items by their match score combined with their frecency bonus. Items
+    /// with the same score keep the order of the matcher.
+    fn rank(&mut self) {
+        let snapshot = self.matcher.snapshot();
+        let count = snapshot.matched_item_count();
+        self.ranked_count = count as usize;
+        self.rerank = false;
+
+        // The fuzzy matcher matches every item in regex mode
+        let regex = self
+            .regex
+            .as_ref()
+            .filter(|_| self.match_mode == MatchMode::Regex);
+        let invalid_regex = regex.is_none() && self.match_mode == MatchMode::Regex;
+        let matched = snapshot
+            .matched_items(..count)
+            .zip(0..)
+            .filter(|(item, _)| match regex {
+                Some(regex) => regex.find(&contract_home(item.data)).is_some(),
+                None => !invalid_regex || self.input.is_empty(),
+            });
+
+        if self.bonus.is_empty() {
+            self.ranked = matched.map(|(_, index)| index).collect();
+            return;
+        }
+
+        let pattern = snapshot.pattern().column_pattern(0);
+        let mut scored = matched
+            .map(|(item, index)| {
+                let score = pattern
+                    .score(item.matcher_columns[0].slice(..), &mut self.scorer)
+                    .unwrap_or(0);
+                let bonus = self.bonus.get(item.data).copied().unwrap_or(0);
+                (index, score + bonus)
+            })
+            .collect::<Vec<_>>();
+        scored.sort_by_key(|&(_, score)| Reverse(score));
+        self.ranked = scored.into_iter().map(|(index, _)| index).collect();
+    }
+
+    fn poll_refresh(&mut self) {
+        if !self.refresh.as_ref().is_some_and(|h| h.is_finished()) {
+            return;
+        }
+
+        if let Some(Ok(Some(items))) = self.refresh.take().map(|h| h.join()) {
+            let shared = self.injector.clone();
+            self.replace_items(&mut lock(&shared), items);
+        }
+    }
+
+    /// Start generating the preview of the item under the cursor once the previous one is done.
+    fn poll_preview(&mut self) {
+        let Some(provider) = self.preview.as_ref() else {
+            return;
+        };
+
+        if self
+            .preview_job
+            .as_ref()
+            .is_some_and(|(_, h)| h.is_finished())
+        {
+            if let Some((item, handle)) = self.preview_job.take() {
+                self.preview_lines = handle.join().unwrap_or_default();
+                self.preview_item = Some(item);
+            }
+        }
+
+        if !self.show_preview || self.preview_job.is_some() {
+            return;
+        }
+
+        let selected = self.get_selected_text();
+        if selected == self.preview_item {
+            return;
+        }
+
+        match selected {
+            Some(item) => {
+                let provider = provider.clone();
+                let job_item = item.clone();
+                let handle = thread::spawn(move || provider.preview(&job_item));
+                self.preview_job = Some((item, handle));
+            }
+            None => {
+                self.preview_item = None;
+                self.preview_lines.clear();
+            }
+        }
     }
 
     fn get_selected_text(&self) -> Option<String> {
@@ -202,15 +953,23 @@ impl Picker {
             return self
                 .matcher
                 .snapshot()
-                .get_matched_item(index as u32)
+                .get_matched_item(*self.ranked.get(index)?)
                 .map(|item| item.data.to_owned());
         }
 
         None
     }
 
+    fn toggle_mark(&mut self) {
+        if let Some(item) = self.get_selected_text() {
+            if !self.marked.shift_remove(&item) {
+                self.marked.insert(item);
+            }
+        }
+    }
+
     fn move_cursor_up(&mut self) {
-        let item_count = self.matcher.snapshot().matched_item_count() as usize;
+        let item_count = self.ranked.len();
         if item_count == 0 {
             return;
         }
@@ -232,67 +991,254 @@ impl Picker {
         }
     }
 
-    fn move_cursor_left(&mut self) {
-        if self.cursor_pos > 0 {
-            self.cursor_pos -= 1;
+    /// Move the selection by `rows` towards the top of the list, clamped to the matched items
+    fn move_selection(&mut self, rows: isize) {
+        let item_count = self.ranked.len();
+        if item_count == 0 {
+            return;
         }
+
+        let selected = self.selection.selected().unwrap_or(0) as isize;
+        let target = selected
+            .saturating_add(rows)
+            .clamp(0, item_count as isize - 1);
+        self.selection.select(Some(target as usize));
     }
 
-    fn move_cursor_right(&mut self) {
-        if self.cursor_pos < self.filter.len() as u16 {
-            self.cursor_pos += 1;
+    fn backspace(&mut self) {
+        if self.input.backspace() {
+            self.update_matcher_pattern(false);
         }
     }
 
-    fn update_filter(&mut self, c: char) {
-        if self.filter.len() == u16::MAX as usize {
-            return;
+    fn delete(&mut self) {
+        if self.input.delete() {
+            self.update_matcher_pattern(false);
         }
+    }
 
-        let prev_filter = self.filter.clone();
-        self.filter.insert(self.cursor_pos as usize, c);
-        self.cursor_pos += 1;
+    /// Insert typed or pasted text at the cursor. Line breaks and tabs become spaces and other
+    /// control characters are dropped.
+    fn insert_text(&mut self, text: &str) {
+        let text = text
+            .chars()
+            .map(|c| match c {
+                '\n' | '\t' => ' ',
+                c => c,
+            })
+            .filter(|c| !c.is_control())
+            .collect::<String>();
 
-        self.update_matcher_pattern(&prev_filter);
+        // Only text added at the end narrows down the previous matches
+        let append = self.input.cursor() == self.input.text().len();
+        if self.input.insert(&text) {
+            self.update_matcher_pattern(append);
+        }
     }
 
-    fn backspace(&mut self) {
-        if self.cursor_pos == 0 {
-            return;
+    fn delete_range(&mut self, start: usize, end: usize) {
+        if self.input.delete_range(start, end) {
+            self.update_matcher_pattern(false);
         }
+    }
 
-        let prev_filter = self.filter.clone();
-        self.filter.remove(self.cursor_pos as usize - 1);
+    /// Replace the query with an older (`-1`) or newer (`1`) entry of the history. Moving past
+    /// the newest entry restores the query that was typed before browsing.
+    fn browse_history(&mut self, direction: isize) {
+        let Some(history) = self.history.as_ref().filter(|h| !h.0.is_empty()) else {
+            return;
+        };
 
-        self.cursor_pos -= 1;
+        let len = history.0.len();
+        let index = match (self.history_index, direction) {
+            (None, -1) => Some(len - 1),
+            (None, _) => return,
+            (Some(0), -1) => Some(0),
+            (Some(i), -1) => Some(i - 1),
+            (Some(i), _) if i + 1 < len => Some(i + 1),
+            (Some(_), _) => None,
+        };
 
-        if self.filter != prev_filter {
-            self.update_matcher_pattern(&prev_filter);
+        if self.history_index.is_none() {
+            self.history_draft = self.input.text().to_string();
         }
+        self.input.set(match index {
+            Some(i) => history.0[i].clone(),
+            None => std::mem::take(&mut self.history_draft),
+        });
+        self.history_index = index;
+        self.update_matcher_pattern(false);
     }
 
-    fn delete(&mut self) {
-        if (self.cursor_pos as usize) == self.filter.len() {
+    /// Update the pattern of the matcher after the filter or the match mode changed. `append`
+    /// is true if the new filter only adds to the end of the previous one.
+    fn update_matcher_pattern(&mut self, append: bool) {
+        // The prompt holds a new name instead of the query while renaming
+        if self.renaming.is_some() {
             return;
         }
 
-        let prev_filter = self.filter.clone();
-        self.filter.remove(self.cursor_pos as usize);
-
-        if self.filter != prev_filter {
-            self.update_matcher_pattern(&prev_filter);
-        }
-    }
-
-    fn update_matcher_pattern(&mut self, prev_filter: &str) {
         self.matcher.pattern.reparse(
             0,
-            self.filter.as_str(),
+            &self.match_mode.pattern(self.input.text()),
             CaseMatching::Smart,
             Normalization::Smart,
-            self.filter.starts_with(prev_filter),
+            append,
         );
+
+        if self.match_mode == MatchMode::Regex {
+            self.regex = Regex::new(self.input.text());
+        }
+        self.rerank = true;
     }
 }
 
 fn request_redraw() {}
+
+fn lock(injector: &SharedInjector) -> MutexGuard<'_, Injector<String>> {
+    injector
+        .lock()
+        .expect("picker injector lock is not poisoned")
+}
+
+/// Replace the home directory at the start of `path` with `~`
+pub(super) fn contract_home(path: &str) -> String {
+    let home = dirs_next::home_dir();
+    let home = home.as_ref().and_then(|h| h.to_str()).unwrap_or_default();
+    match path.strip_prefix(home) {
+        Some(rest) if !home.is_empty() && (rest.is_empty() || rest.starts_with('/')) => {
+            format!("~{}", rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Style the graphemes of `text` at the match `indices`. Paths wider than `width` are
+/// shortened with [`elide`].
+fn highlight(text: &str, indices: &[u32], width: usize, style: Style) -> Vec<Span<'static>> {
+    let mut spans = vec![];
+    let mut current = String::new();
+    let mut current_matched = false;
+    for (index, grapheme) in elide(text, width) {
+        let matched = index.is_some_and(|i| indices.binary_search(&(i as u32)).is_ok());
+        if matched != current_matched && !current.is_empty() {
+            let content = std::mem::take(&mut current);
+            spans.push(match current_matched {
+                true => Span::styled(content, style),
+                false => Span::raw(content),
+            });
+        }
+        current_matched = matched;
+        current.push_str(grapheme);
+    }
+
+    if !current.is_empty() {
+        spans.push(match current_matched {
+            true => Span::styled(current, style),
+            false => Span::raw(current),
+        });
+    }
+    spans
+}
+
+/// Replace the middle components of a path wider than `width` columns with `…`, keeping the
+/// first component and as many of the last components as fit. Each grapheme is paired with
+/// its index in `text`, the inserted ellipsis has no index.
+fn elide(text: &str, width: usize) -> Vec<(Option<usize>, &str)> {
+    let graphemes = text.graphemes(true).collect::<Vec<_>>();
+    let all = graphemes
+        .iter()
+        .copied()
+        .enumerate()
+        .map(|(i, g)| (Some(i), g));
+    if text.width() <= width {
+        return all.collect();
+    }
+
+    // Display width of the graphemes from each index to the end
+    let mut tail_widths = graphemes
+        .iter()
+        .rev()
+        .scan(0, |total, g| {
+            *total += g.width();
+            Some(*total)
+        })
+        .collect::<Vec<_>>();
+    tail_widths.reverse();
+
+    let slashes = graphemes
+        .iter()
+        .enumerate()
+        .filter(|(_, &g)| g == "/")
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    // The head is the first component with its slashes such as `~/` or `/home/`
+    let Some(&head_end) = slashes.iter().find(|&&i| i > 0) else {
+        return all.collect();
+    };
+    let Some(&last) = slashes.last().filter(|&&i| i > head_end) else {
+        return all.collect();
+    };
+    let head_width = tail_widths[0] - tail_widths[head_end + 1];
+    let tail_start = slashes
+        .iter()
+        .rev()
+        .copied()
+        .filter(|&i| i > head_end)
+        .take_while(|&i| head_width + 1 + tail_widths[i] <= width)
+        .last()
+        .unwrap_or(last);
+
+    all.clone()
+        .take(head_end + 1)
+        .chain(std::iter::once((None, "…")))
+        .chain(all.skip(tail_start))
+        .collect()
+}
+
+/// Indices of the graphemes of `text` overlapping the byte `range`
+fn grapheme_indices(text: &str, range: Range<usize>) -> impl Iterator<Item = u32> + '_ {
+    text.grapheme_indices(true)
+        .enumerate()
+        .filter(move |(_, (start, grapheme))| {
+            *start < range.end && start + grapheme.len() > range.start
+        })
+        .map(|(index, _)| index as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::style::Style;
+
+    use super::{elide, grapheme_indices, highlight};
+
+    fn text(graphemes: Vec<(Option<usize>, &str)>) -> String {
+        graphemes.into_iter().map(|(_, g)| g).collect()
+    }
+
+    #[test]
+    fn keep_combining_marks() {
+        let path = "~/code/cafe\u{301}";
+        assert_eq!(text(elide(path, 80)), path);
+
+        let spans = highlight(path, &[10], 80, Style::new());
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1].content, "e\u{301}");
+    }
+
+    #[test]
+    fn elide_by_display_width() {
+        assert_eq!(text(elide("~/项目/代码/工作/api", 14)), "~/…/工作/api");
+        assert_eq!(text(elide("~/ab/cd/ef/api", 14)), "~/ab/cd/ef/api");
+        assert_eq!(text(elide("~/ab/cd/ef/api", 10)), "~/…/ef/api");
+    }
+
+    #[test]
+    fn map_byte_range_to_graphemes() {
+        let path = "~/cafe\u{301}/api";
+        let start = path.find('e').unwrap();
+        let indices = grapheme_indices(path, start..start + 1).collect::<Vec<_>>();
+        assert_eq!(indices, [5]);
+    }
+}
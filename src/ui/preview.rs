@@ -0,0 +1,159 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+use gix::bstr::ByteSlice;
+
+use super::Item;
+use crate::mux::Multiplexer;
+
+/// Number of lines of a readme shown in the preview
+const README_LINES: usize = 20;
+
+/// Produces the lines shown in the preview area of the picker for the item under the cursor.
+/// Previews are generated on a background thread so slow providers do not block the picker.
+pub trait PreviewProvider: Send + Sync {
+    fn preview(&self, item: &str) -> Vec<String>;
+}
+
+/// Git state and readme of a workspace path.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WorkspacePreview;
+
+impl PreviewProvider for WorkspacePreview {
+    fn preview(&self, item: &str) -> Vec<String> {
+        let path = Path::new(item);
+        let mut lines = vec![];
+
+        if let Ok(repo) = gix::open(path) {
+            if let Some(branch) = repo
+                .head()
+                .ok()
+                .and_then(|head| head.referent_name().map(|r| r.shorten().to_string()))
+            {
+                lines.push(format!("branch: {}", branch));
+            }
+
+            if let Some(changes) = changed_files(path) {
+                match changes {
+                    0 => lines.push("status: clean".to_string()),
+                    n => lines.push(format!("status: {} changed files", n)),
+                }
+            }
+
+            if let Ok(commit) = repo.head_commit() {
+                let id = commit
+                    .short_id()
+                    .map(|id| id.to_string())
+                    .unwrap_or_default();
+                let summary = commit
+                    .message()
+                    .map(|m| m.summary().to_str_lossy().to_string())
+                    .unwrap_or_default();
+                lines.push(format!("commit: {} {}", id, summary));
+            }
+
+            lines.push(String::new());
+        }
+
+        lines.extend(readme(path));
+        lines
+    }
+}
+
+/// Windows and the active pane of a running session.
+#[derive(Debug, Clone)]
+pub struct SessionPreview {
+    mux: Arc<dyn Multiplexer>,
+}
+
+impl SessionPreview {
+    pub fn new(mux: Arc<dyn Multiplexer>) -> Self {
+        Self { mux }
+    }
+}
+
+impl PreviewProvider for SessionPreview {
+    fn preview(&self, item: &str) -> Vec<String> {
+        let mut lines = self
+            .mux
+            .list_windows(item)
+            .into_iter()
+            .map(|w| format!("window: {}", w))
+            .collect::<Vec<_>>();
+        lines.push(String::new());
+
+        if let Ok(content) = self.mux.capture_pane(item) {
+            lines.extend(content.lines().map(|l| l.to_string()));
+        }
+        lines
+    }
+}
+
+/// Preview of a list that mixes sessions and workspaces. Items are told apart by the running
+/// sessions like the unified picker does.
+#[derive(Debug, Clone)]
+pub struct UnifiedPreview {
+    session: SessionPreview,
+}
+
+impl UnifiedPreview {
+    pub fn new(mux: Arc<dyn Multiplexer>) -> Self {
+        Self {
+            session: SessionPreview::new(mux),
+        }
+    }
+}
+
+impl PreviewProvider for UnifiedPreview {
+    fn preview(&self, item: &str) -> Vec<String> {
+        match Item::new(item, &self.session.mux.list_sessions()) {
+            Item::Workspace(_) => WorkspacePreview.preview(item),
+            Item::Session(_) => self.session.preview(item),
+        }
+    }
+}
+
+/// Number of changed files in the working tree. `None` if git is not available.
+fn changed_files(path: &Path) -> Option<usize> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(path)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(ByteSlice::lines(output.stdout.as_slice()).count())
+}
+
+fn readme(path: &Path) -> Vec<String> {
+    let file = std::fs::read_dir(path).ok().and_then(|entries| {
+        entries
+            .filter_map(Result::ok)
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .find(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.to_lowercase().starts_with("readme"))
+            })
+    });
+
+    file.and_then(|file| File::open(file).ok())
+        .map(|file| {
+            BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .take(README_LINES)
+                .collect()
+        })
+        .unwrap_or_default()
+}
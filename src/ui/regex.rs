@@ -0,0 +1,55 @@
+use std::ops::Range;
+
+/// Regular expression of the regex match mode of the picker. Like the fuzzy matcher it ignores
+/// case unless the pattern contains an uppercase character.
+#[derive(Debug, Clone)]
+pub struct Regex(regex::Regex);
+
+impl Regex {
+    /// Parse `pattern`, `None` if it is not a valid expression.
+    pub fn new(pattern: &str) -> Option<Self> {
+        // Escapes such as `\W` are not uppercase characters
+        let ignore_case = !pattern
+            .split('\\')
+            .enumerate()
+            .flat_map(|(i, part)| part.chars().skip(usize::from(i > 0)))
+            .any(char::is_uppercase);
+
+        regex::RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .ok()
+            .map(Self)
+    }
+
+    /// Byte range of the leftmost match in `text`
+    pub fn find(&self, text: &str) -> Option<Range<usize>> {
+        self.0.find(text).map(|found| found.range())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Regex;
+
+    #[test]
+    fn reject_invalid_pattern() {
+        assert!(Regex::new("(api").is_none());
+        assert!(Regex::new("[a-").is_none());
+    }
+
+    #[test]
+    fn find_byte_range() {
+        let regex = Regex::new("a.i$").unwrap();
+        assert_eq!(regex.find("~/src/api"), Some(6..9));
+        assert_eq!(regex.find("~/프로젝트/api"), Some(15..18));
+        assert_eq!(regex.find("~/src/api/web"), None);
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(Regex::new("api").unwrap().find("~/src/API").is_some());
+        assert!(Regex::new("Api").unwrap().find("~/src/api").is_none());
+        assert!(Regex::new(r"\Wapi").unwrap().find("~/src/API").is_some());
+    }
+}
@@ -1,37 +1,65 @@
 use std::{io, panic};
 
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use miette::{IntoDiagnostic, Result};
+use ratatui::{backend::CrosstermBackend, Terminal, TerminalOptions, Viewport};
+
+use crate::config::Mode;
 
 use super::{event::EventHandler, Picker};
 pub type CrosstermTerminal = ratatui::Terminal<ratatui::backend::CrosstermBackend<std::io::Stderr>>;
 
+/// Smallest number of lines an inline picker is drawn with
+const MIN_HEIGHT: u16 = 3;
+
 pub struct Tui {
     pub(crate) events: EventHandler,
     terminal: CrosstermTerminal,
+    /// Drawn on the alternate screen instead of inline below the cursor
+    fullscreen: bool,
     is_running: bool,
 }
 
 impl Tui {
-    pub fn new(terminal: CrosstermTerminal, events: EventHandler) -> Self {
-        Self {
+    pub fn new(mode: Mode, events: EventHandler) -> Result<Self> {
+        let viewport = match mode {
+            Mode::Full => Viewport::Fullscreen,
+            Mode::Lines(lines) => Viewport::Inline(lines.max(MIN_HEIGHT)),
+            Mode::Percentage(percentage) => {
+                let (_, rows) = terminal::size().into_diagnostic()?;
+                Viewport::Inline(((rows as f32 * percentage).round() as u16).max(MIN_HEIGHT))
+            }
+        };
+        let fullscreen = matches!(viewport, Viewport::Fullscreen);
+        let terminal = Terminal::with_options(
+            CrosstermBackend::new(io::stderr()),
+            TerminalOptions { viewport },
+        )
+        .into_diagnostic()?;
+
+        Ok(Self {
             terminal,
             events,
+            fullscreen,
             is_running: true,
-        }
+        })
     }
 
     pub fn enter(&mut self) -> Result<()> {
         terminal::enable_raw_mode().into_diagnostic()?;
-        crossterm::execute!(io::stderr(), EnterAlternateScreen, EnableMouseCapture)
+        if self.fullscreen {
+            crossterm::execute!(io::stderr(), EnterAlternateScreen).into_diagnostic()?;
+        }
+        crossterm::execute!(io::stderr(), EnableMouseCapture, EnableBracketedPaste)
             .into_diagnostic()?;
 
+        let fullscreen = self.fullscreen;
         let panic_hook = panic::take_hook();
         panic::set_hook(Box::new(move |panic| {
-            Self::reset().expect("Failed to reset the terminal");
+            Self::reset(fullscreen).expect("Failed to reset the terminal");
             panic_hook(panic);
         }));
 
@@ -40,20 +68,32 @@ impl Tui {
         Ok(())
     }
 
-    fn reset() -> Result<()> {
+    fn reset(fullscreen: bool) -> Result<()> {
         terminal::disable_raw_mode().into_diagnostic()?;
-        crossterm::execute!(io::stderr(), LeaveAlternateScreen, DisableMouseCapture)
+        if fullscreen {
+            crossterm::execute!(io::stderr(), LeaveAlternateScreen).into_diagnostic()?;
+        }
+        crossterm::execute!(io::stderr(), DisableMouseCapture, DisableBracketedPaste)
             .into_diagnostic()?;
         Ok(())
     }
 
     pub fn exit(&mut self) -> Result<()> {
-        Self::reset()?;
+        // Remove the inline picker and leave the cursor where the picker started
+        if !self.fullscreen {
+            self.terminal.clear().into_diagnostic()?;
+        }
+        Self::reset(self.fullscreen)?;
         self.terminal.show_cursor().into_diagnostic()?;
         self.is_running = false;
         Ok(())
     }
 
+    /// Resize the viewport to the current size of the terminal
+    pub fn resize(&mut self) -> Result<()> {
+        self.terminal.autoresize().into_diagnostic()
+    }
+
     pub fn draw(&mut self, picker: &mut Picker) -> Result<()> {
         self.terminal
             .draw(|frame| picker.render(frame))
@@ -2,7 +2,6 @@ use miette::IntoDiagnostic;
 use miette::Result;
 use std::env::var;
 use std::fs::File;
-use std::fs::OpenOptions;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -29,8 +28,16 @@ pub fn get_config(components: &[&str]) -> PathBuf {
     path
 }
 
+/// Replace the characters tmux does not allow in session and window names, `:` and `.`, as
+/// well as whitespace with `_`
 pub fn format_name(name: &str) -> String {
-    name.replace('.', "_")
+    name.chars()
+        .map(|c| match c {
+            ':' | '.' => '_',
+            c if c.is_whitespace() || c.is_control() => '_',
+            c => c,
+        })
+        .collect()
 }
 
 pub fn get_local(components: &[&str]) -> PathBuf {
@@ -63,31 +70,31 @@ pub fn write_content<P>(path: P, content: &str) -> Result<()>
 where
     P: AsRef<Path> + std::fmt::Debug,
 {
-    std::fs::create_dir_all(path.as_ref().parent().unwrap()).into_diagnostic()?;
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&path)
-        .into_diagnostic()?;
-
-    file.write_all(content.as_bytes()).into_diagnostic()?;
-
-    Ok(())
+    write(path, |file| {
+        file.write_all(content.as_bytes()).into_diagnostic()
+    })
 }
 
+/// Write a file through `write_fn`. The content is written to a temporary file next to `path`
+/// that replaces it once complete so readers never see a partially written file.
 pub fn write<P, F>(path: P, write_fn: F) -> Result<()>
 where
     P: AsRef<Path>,
     F: FnOnce(&mut File) -> Result<()>,
 {
-    std::fs::create_dir_all(path.as_ref().parent().unwrap()).into_diagnostic()?;
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(&path)
-        .into_diagnostic()?;
+    let path = path.as_ref();
+    std::fs::create_dir_all(path.parent().unwrap()).into_diagnostic()?;
+
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(".{}.tmp", std::process::id()));
+    let tmp = path.with_file_name(name);
+    let mut file = File::create(&tmp).into_diagnostic()?;
 
-    write_fn(&mut file)
+    let result = write_fn(&mut file)
+        .and_then(|_| file.sync_all().into_diagnostic())
+        .and_then(|_| std::fs::rename(&tmp, path).into_diagnostic());
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
 }
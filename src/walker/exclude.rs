@@ -0,0 +1,256 @@
+use std::path::{Path, PathBuf};
+
+use gix::{
+    bstr::{BStr, BString, ByteSlice},
+    glob::{pattern::Case, wildmatch, Pattern},
+};
+
+/// Files at the root of a workspace that are read when `respect_ignore` is enabled.
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+#[derive(Debug, Clone)]
+enum Rule {
+    /// A single path component such as `node_modules`
+    Name(String),
+    /// An absolute path and everything below it
+    Prefix(PathBuf),
+    /// A glob matched against the absolute path, or the last path component if it has no `/`
+    Glob(BString),
+    /// A pattern from an ignore file matched relative to `root`
+    Ignore { root: PathBuf, pattern: Pattern },
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    rule: Rule,
+    negated: bool,
+}
+
+/// Rules deciding which paths are pruned by the workspace walker. Like gitignore the last rule
+/// matching a path wins, so a negated rule (`!path`) can re-include a path excluded before it.
+#[derive(Debug, Clone, Default)]
+pub struct Exclude {
+    entries: Vec<Entry>,
+}
+
+impl Exclude {
+    pub fn new<'a, I>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let entries = patterns
+            .into_iter()
+            .filter_map(|pattern| {
+                let (negated, pattern) = match pattern.strip_prefix('!') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, pattern.as_str()),
+                };
+
+                if pattern.is_empty() {
+                    return None;
+                }
+
+                let pattern = shellexpand::tilde(pattern);
+                let rule = if pattern.contains(['*', '?', '[']) {
+                    Rule::Glob(pattern.as_ref().into())
+                } else if pattern.contains('/') {
+                    Rule::Prefix(PathBuf::from(pattern.as_ref()))
+                } else {
+                    Rule::Name(pattern.to_string())
+                };
+
+                Some(Entry { rule, negated })
+            })
+            .collect();
+
+        Self { entries }
+    }
+
+    /// Append the patterns of the ignore files found in the workspace `root`.
+    pub fn with_ignore_files(mut self, root: &Path) -> Self {
+        for file in IGNORE_FILES {
+            let Ok(content) = std::fs::read(root.join(file)) else {
+                continue;
+            };
+
+            for line in content.lines() {
+                let line = line.trim();
+                if line.starts_with(b"#") {
+                    continue;
+                }
+
+                if let Some(pattern) = Pattern::from_bytes(line) {
+                    self.entries.push(Entry {
+                        negated: pattern.is_negative(),
+                        rule: Rule::Ignore {
+                            root: root.to_owned(),
+                            pattern,
+                        },
+                    });
+                }
+            }
+        }
+
+        self
+    }
+
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.rule.matches(path, is_dir))
+            .is_some_and(|entry| !entry.negated)
+    }
+
+    /// True if a negated rule could re-include a path below `path`. Excluded directories are
+    /// still walked in this case.
+    pub fn has_negated_below(&self, path: &Path) -> bool {
+        self.entries
+            .iter()
+            .filter(|entry| entry.negated)
+            .filter_map(|entry| entry.rule.base())
+            .any(|base| base.starts_with(path))
+    }
+}
+
+impl Rule {
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        match self {
+            Rule::Name(name) => path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n == name),
+            Rule::Prefix(prefix) => path.starts_with(prefix),
+            Rule::Glob(glob) => {
+                let subject = if glob.contains(&b'/') {
+                    path.to_str()
+                } else {
+                    path.file_name().and_then(|n| n.to_str())
+                };
+                subject.is_some_and(|p| {
+                    gix::glob::wildmatch(
+                        glob.as_bstr(),
+                        p.into(),
+                        wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+                    )
+                })
+            }
+            Rule::Ignore { root, pattern } => {
+                let Some(relative) = path.strip_prefix(root).ok().and_then(|p| p.to_str()) else {
+                    return false;
+                };
+                let relative: &BStr = relative.into();
+                pattern.matches_repo_relative_path(
+                    relative,
+                    relative.rfind_byte(b'/').map(|p| p + 1),
+                    Some(is_dir),
+                    Case::Sensitive,
+                    wildmatch::Mode::NO_MATCH_SLASH_LITERAL,
+                )
+            }
+        }
+    }
+
+    /// Directory every path matched by this rule is in or is, if it is known.
+    fn base(&self) -> Option<PathBuf> {
+        match self {
+            Rule::Name(_) => None,
+            Rule::Prefix(prefix) => Some(prefix.to_owned()),
+            Rule::Glob(glob) => {
+                let literal = glob
+                    .find_byteset(b"*?[")
+                    .map_or(glob.as_bstr(), |pos| glob[..pos].as_bstr());
+                let dir = literal.rfind_byte(b'/').map(|pos| &literal[..pos])?;
+                dir.to_str().ok().map(PathBuf::from)
+            }
+            Rule::Ignore { root, pattern } => {
+                let literal = pattern.text.as_bstr();
+                let literal = pattern
+                    .first_wildcard_pos
+                    .map_or(literal, |pos| literal[..pos].as_bstr());
+                let dir = literal.rfind_byte(b'/').map(|pos| &literal[..pos])?;
+                dir.to_str().ok().map(|dir| root.join(dir))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::Exclude;
+
+    fn exclude(patterns: &[&str]) -> Exclude {
+        Exclude::new(&patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>())
+    }
+
+    fn home(path: &str) -> PathBuf {
+        dirs_next::home_dir().unwrap().join(path)
+    }
+
+    #[test]
+    fn exclude_names() {
+        let exclude = exclude(&["node_modules"]);
+        assert!(exclude.is_excluded(Path::new("/src/web/node_modules"), true));
+        assert!(!exclude.is_excluded(Path::new("/src/web/node_modules_old"), true));
+        assert!(!exclude.is_excluded(Path::new("/src/web"), true));
+    }
+
+    #[test]
+    fn exclude_globs() {
+        let exclude = exclude(&["**/vendor/**", "*.tmp"]);
+        assert!(exclude.is_excluded(Path::new("/src/api/vendor/lib"), true));
+        assert!(!exclude.is_excluded(Path::new("/src/api/vendors/lib"), true));
+        assert!(exclude.is_excluded(Path::new("/src/api/build.tmp"), true));
+        assert!(!exclude.is_excluded(Path::new("/src/api"), true));
+    }
+
+    #[test]
+    fn exclude_prefixes() {
+        let exclude = exclude(&["~/code/archive"]);
+        assert!(exclude.is_excluded(&home("code/archive"), true));
+        assert!(exclude.is_excluded(&home("code/archive/old"), true));
+        assert!(!exclude.is_excluded(&home("code/archived"), true));
+    }
+
+    #[test]
+    fn reinclude_negated_paths() {
+        let exclude = exclude(&["~/code/archive/*", "!~/code/archive/keep"]);
+        assert!(exclude.is_excluded(&home("code/archive/old"), true));
+        assert!(!exclude.is_excluded(&home("code/archive/keep"), true));
+        assert!(!exclude.is_excluded(&home("code/archive"), true));
+
+        assert!(exclude.has_negated_below(&home("code")));
+        assert!(exclude.has_negated_below(&home("code/archive")));
+        assert!(!exclude.has_negated_below(&home("code/archive/old")));
+        assert!(!exclude.has_negated_below(&home("src")));
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let exclude = exclude(&["!~/code/archive/keep", "~/code/archive"]);
+        assert!(exclude.is_excluded(&home("code/archive/keep"), true));
+    }
+
+    #[test]
+    fn exclude_ignore_file_patterns() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(
+            root.path().join(".gitignore"),
+            "# build output\ntarget/\n*.log\n!keep.log\nbuild/\n!build/keep\n",
+        )
+        .unwrap();
+        let exclude = Exclude::default().with_ignore_files(root.path());
+
+        assert!(exclude.is_excluded(&root.path().join("target"), true));
+        assert!(!exclude.is_excluded(&root.path().join("target"), false));
+        assert!(exclude.is_excluded(&root.path().join("debug.log"), false));
+        assert!(!exclude.is_excluded(&root.path().join("keep.log"), false));
+        assert!(exclude.is_excluded(&root.path().join("build"), true));
+        assert!(!exclude.is_excluded(&root.path().join("build/keep"), true));
+
+        assert!(exclude.has_negated_below(&root.path().join("build")));
+        assert!(!exclude.has_negated_below(&root.path().join("target")));
+    }
+}
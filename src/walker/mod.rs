@@ -0,0 +1,169 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use gix::repository::Kind;
+use indexmap::IndexSet;
+
+use crate::config::Config;
+
+mod exclude;
+
+use exclude::Exclude;
+
+/// Why a directory is considered a workspace.
+#[derive(Debug, Clone, Copy)]
+enum Project {
+    /// A git directory or bare repository
+    Git(Kind),
+    /// A configured marker file or directory
+    Marker,
+}
+
+impl Project {
+    fn is_bare(&self) -> bool {
+        match self {
+            Project::Git(kind) => kind.is_bare(),
+            Project::Marker => false,
+        }
+    }
+}
+
+pub trait Walker {
+    /// Walk the search paths and call `f` with each workspace as soon as it is found.
+    fn walk<F: FnMut(String)>(&self, f: F);
+
+    fn paths_from_walk(&self) -> Vec<String> {
+        let mut result = vec![];
+        self.walk(|path| result.push(path));
+        result
+    }
+}
+
+impl Walker for Config {
+    fn walk<F: FnMut(String)>(&self, mut f: F) {
+        self.search.single.iter().cloned().for_each(&mut f);
+        let exclude_rules = Exclude::new(&self.exclude_path);
+        let project_markers = Arc::new(self.markers.clone());
+
+        for workspace in &self.search.workspace {
+            let exclude = if self.respect_ignore {
+                exclude_rules
+                    .clone()
+                    .with_ignore_files(Path::new(&workspace.path))
+            } else {
+                exclude_rules.clone()
+            };
+            let markers = project_markers.clone();
+
+            // The repository marker of a workspace is one level below the workspace itself
+            let depth = workspace.depth.unwrap_or(self.depth);
+            let walk =
+                jwalk::WalkDirGeneric::<((), Option<Project>)>::new(Path::new(&workspace.path))
+                    .follow_links(false)
+                    .skip_hidden(false)
+                    .max_depth(depth.saturating_add(1));
+
+            let additions = walk
+                .process_read_dir(move |_depth, _path, _read_dir_state, siblings| {
+                    siblings.retain(|entry_result| {
+                        entry_result
+                            .as_ref()
+                            .map(|entry| {
+                                let path = entry.path();
+                                !exclude.is_excluded(&path, entry.file_type().is_dir())
+                                    || exclude.has_negated_below(&path)
+                            })
+                            .unwrap_or(false)
+                    });
+
+                    let mut found_any_repo = false;
+                    let mut found_bare_repo = false;
+                    let mut found_workdir = false;
+                    for entry in siblings.iter_mut().flatten() {
+                        let path = entry.path();
+                        let project = match is_repository(&path) {
+                            Some(kind) => Some(Project::Git(kind)),
+                            None => is_marker(&path, &markers).then_some(Project::Marker),
+                        };
+
+                        if let Some(project) = project {
+                            let is_bare = project.is_bare();
+                            // A workspace can contain both a git directory and marker files. Only
+                            // report the workspace once.
+                            if !is_bare && found_workdir {
+                                continue;
+                            }
+
+                            found_workdir |= !is_bare;
+                            entry.client_state = Some(project);
+                            entry.read_children_path = None;
+
+                            found_any_repo = true;
+                            found_bare_repo = is_bare;
+                        }
+                    }
+                    // Only return paths which are projects are further participating in the traversal
+                    // Don't let bare repositories cause siblings to be pruned.
+                    if found_any_repo && !found_bare_repo {
+                        siblings.retain(|e| {
+                            e.as_ref()
+                                .map(|e| e.client_state.is_some())
+                                .unwrap_or(false)
+                        });
+                    }
+                })
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter_map(|mut e| {
+                    e.client_state
+                        .take()
+                        .map(|state| into_workdir(e.path(), &state).display().to_string())
+                });
+
+            additions.for_each(&mut f);
+        }
+    }
+}
+
+fn is_repository(path: &Path) -> Option<Kind> {
+    // Can be git dir or worktree checkout (file)
+    if path.file_name() != Some(OsStr::new(".git")) && path.extension() != Some(OsStr::new("git")) {
+        return None;
+    }
+
+    if path.is_dir() {
+        if path.join("HEAD").is_file() && path.join("config").is_file() {
+            gix::discover::is_git(path).ok().map(Into::into)
+        } else {
+            None
+        }
+    } else {
+        // git files are always worktrees
+        Some(Kind::WorkTree { is_linked: true })
+    }
+}
+
+fn is_marker(path: &Path, markers: &IndexSet<String>) -> bool {
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .is_some_and(|name| markers.contains(name))
+}
+
+fn into_workdir(git_dir: PathBuf, project: &Project) -> PathBuf {
+    let is_git_dir = match project {
+        Project::Git(kind) => matches!(kind, Kind::Bare) || gix::discover::is_bare(&git_dir),
+        Project::Marker => false,
+    };
+
+    if is_git_dir {
+        git_dir
+    } else {
+        git_dir
+            .parent()
+            .expect("git is never in the root")
+            .to_owned()
+    }
+}